@@ -0,0 +1,419 @@
+//! A procedural macro alternative to generating config structs in a
+//! `build.rs` file.
+//!
+//! ```rust,ignore
+//! config_struct_macros::include_config!("config.toml", struct_name = "Config");
+//!
+//! fn main() {
+//!     println!("{}", CONFIG.name);
+//! }
+//! ```
+//!
+//! The path is resolved relative to the `CARGO_MANIFEST_DIR` of the crate
+//! using the macro, and the generated code is expanded in place. The macro
+//! produces exactly the same items as `config_to_struct::generate_struct`.
+
+use std::path::PathBuf;
+
+use config_struct::{
+    DynamicLoading, Edition, FloatSize, Format, IntSize, SerdeSupport, StructNaming, StructOptions,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    braced, bracketed,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, LitBool, LitInt, LitStr, Token,
+};
+
+/// Generate structs from a config file at compile time.
+///
+/// The first argument is the path of the config file, relative to
+/// the crate root. It can be followed by any of the following
+/// `StructOptions` settings, which have the same defaults:
+///
+/// ```rust,ignore
+/// config_struct_macros::include_config!(
+///     "config.toml",
+///     format = Toml,
+///     struct_name = "Config",
+///     const_name = "CONFIG",
+///     generate_const = true,
+///     derived_traits = ["Debug", "Clone"],
+///     serde_support = Mixed { serialize: false, deserialize: true },
+///     use_serde_derive_crate = false,
+///     generate_load_fns = true,
+///     dynamic_loading = DebugOnly,
+///     env_prefix = "APP",
+///     default_float_size = F64,
+///     default_int_size = I64,
+///     max_array_size = 0,
+///     preserve_key_order = false,
+///     deduplicate_structs = false,
+///     report_all_errors = false,
+///     sanitize_keys = false,
+///     raw_identifiers = false,
+///     edition = Rust2024,
+///     struct_naming = PascalCaseKey,
+///     struct_name_overrides = { "database.pool": "PoolConfig" },
+/// );
+/// ```
+#[proc_macro]
+pub fn include_config(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as IncludeConfig);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct IncludeConfig {
+    path: LitStr,
+    options: StructOptions,
+}
+
+impl Parse for IncludeConfig {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut options = StructOptions::default();
+        let mut settings = Vec::new();
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let setting: Ident = input.parse()?;
+            if settings.contains(&setting) {
+                return Err(syn::Error::new(
+                    setting.span(),
+                    format!("duplicate setting `{setting}`"),
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            parse_setting(&setting, input, &mut options)?;
+            settings.push(setting);
+        }
+
+        Ok(IncludeConfig { path, options })
+    }
+}
+
+fn parse_setting(
+    setting: &Ident,
+    input: ParseStream,
+    options: &mut StructOptions,
+) -> syn::Result<()> {
+    match setting.to_string().as_str() {
+        "format" => options.format = Some(parse_format(input)?),
+        "struct_name" => options.struct_name = input.parse::<LitStr>()?.value(),
+        "const_name" => options.const_name = Some(input.parse::<LitStr>()?.value()),
+        "generate_const" => options.generate_const = input.parse::<LitBool>()?.value,
+        "derived_traits" => {
+            let content;
+            bracketed!(content in input);
+            options.derived_traits = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?
+                .iter()
+                .map(LitStr::value)
+                .collect();
+        }
+        "serde_support" => options.serde_support = parse_serde_support(input)?,
+        "use_serde_derive_crate" => {
+            options.use_serde_derive_crate = input.parse::<LitBool>()?.value;
+        }
+        "generate_load_fns" => options.generate_load_fns = input.parse::<LitBool>()?.value,
+        "dynamic_loading" => {
+            options.dynamic_loading = parse_variant(
+                input,
+                &[
+                    ("Always", DynamicLoading::Always),
+                    ("DebugOnly", DynamicLoading::DebugOnly),
+                    ("Never", DynamicLoading::Never),
+                ],
+            )?;
+        }
+        "env_prefix" => options.env_prefix = Some(input.parse::<LitStr>()?.value()),
+        "default_float_size" => {
+            options.default_float_size =
+                parse_variant(input, &[("F32", FloatSize::F32), ("F64", FloatSize::F64)])?;
+        }
+        "default_int_size" => {
+            options.default_int_size = parse_variant(
+                input,
+                &[
+                    ("I8", IntSize::I8),
+                    ("I16", IntSize::I16),
+                    ("I32", IntSize::I32),
+                    ("I64", IntSize::I64),
+                    ("ISize", IntSize::ISize),
+                ],
+            )?;
+        }
+        "max_array_size" => options.max_array_size = input.parse::<LitInt>()?.base10_parse()?,
+        "preserve_key_order" => options.preserve_key_order = input.parse::<LitBool>()?.value,
+        "deduplicate_structs" => options.deduplicate_structs = input.parse::<LitBool>()?.value,
+        "report_all_errors" => options.report_all_errors = input.parse::<LitBool>()?.value,
+        "sanitize_keys" => options.sanitize_keys = input.parse::<LitBool>()?.value,
+        "raw_identifiers" => options.raw_identifiers = input.parse::<LitBool>()?.value,
+        "edition" => {
+            options.edition = parse_variant(
+                input,
+                &[
+                    ("Rust2015", Edition::Rust2015),
+                    ("Rust2018", Edition::Rust2018),
+                    ("Rust2021", Edition::Rust2021),
+                    ("Rust2024", Edition::Rust2024),
+                ],
+            )?;
+        }
+        "struct_naming" => {
+            options.struct_naming = parse_variant(
+                input,
+                &[
+                    ("Underscored", StructNaming::Underscored),
+                    ("PascalCaseKeyPath", StructNaming::PascalCaseKeyPath),
+                    ("PascalCaseKey", StructNaming::PascalCaseKey),
+                ],
+            )?;
+        }
+        "struct_name_overrides" => {
+            let content;
+            braced!(content in input);
+            let overrides = Punctuated::<NameOverride, Token![,]>::parse_terminated(&content)?;
+            options.struct_name_overrides = overrides
+                .into_iter()
+                .map(|name_override| (name_override.key_path.value(), name_override.name.value()))
+                .collect();
+        }
+        _ => {
+            return Err(syn::Error::new(
+                setting.span(),
+                format!("unknown setting `{setting}`"),
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_format(input: ParseStream) -> syn::Result<Format> {
+    parse_variant(
+        input,
+        &[
+            #[cfg(feature = "json-parsing")]
+            ("Json", Format::Json),
+            #[cfg(feature = "ron-parsing")]
+            ("Ron", Format::Ron),
+            #[cfg(feature = "toml-parsing")]
+            ("Toml", Format::Toml),
+            #[cfg(feature = "yaml-parsing")]
+            ("Yaml", Format::Yaml),
+        ],
+    )
+}
+
+fn parse_serde_support(input: ParseStream) -> syn::Result<SerdeSupport> {
+    if !input.peek(Ident) || !input.peek2(syn::token::Brace) {
+        return parse_variant(
+            input,
+            &[("No", SerdeSupport::No), ("Yes", SerdeSupport::Yes)],
+        );
+    }
+
+    let variant: Ident = input.parse()?;
+    if variant != "Mixed" {
+        return Err(syn::Error::new(variant.span(), "expected `Mixed`"));
+    }
+
+    let content;
+    braced!(content in input);
+    let (mut serialize, mut deserialize) = (false, false);
+    for field in Punctuated::<MixedField, Token![,]>::parse_terminated(&content)? {
+        if field.name == "serialize" {
+            serialize = field.value.value;
+        } else if field.name == "deserialize" {
+            deserialize = field.value.value;
+        } else {
+            return Err(syn::Error::new(
+                field.name.span(),
+                "expected `serialize` or `deserialize`",
+            ));
+        }
+    }
+
+    Ok(SerdeSupport::Mixed {
+        serialize,
+        deserialize,
+    })
+}
+
+fn parse_variant<T: Copy>(input: ParseStream, variants: &[(&str, T)]) -> syn::Result<T> {
+    let ident: Ident = input.parse()?;
+    variants
+        .iter()
+        .find(|(name, _)| ident == name)
+        .map(|&(_, value)| value)
+        .ok_or_else(|| {
+            let names = variants
+                .iter()
+                .map(|(name, _)| format!("`{name}`"))
+                .collect::<Vec<String>>();
+            syn::Error::new(
+                ident.span(),
+                format!("expected one of {}", names.join(", ")),
+            )
+        })
+}
+
+struct NameOverride {
+    key_path: LitStr,
+    name: LitStr,
+}
+
+impl Parse for NameOverride {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key_path = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let name = input.parse()?;
+        Ok(NameOverride { key_path, name })
+    }
+}
+
+struct MixedField {
+    name: Ident,
+    value: LitBool,
+}
+
+impl Parse for MixedField {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let value = input.parse()?;
+        Ok(MixedField { name, value })
+    }
+}
+
+fn expand(input: &IncludeConfig) -> syn::Result<TokenStream> {
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let filepath = manifest_dir.join(input.path.value());
+
+    let tokens = config_struct::generate_struct_tokens(&filepath, &input.options)
+        .map_err(|error| syn::Error::new(input.path.span(), error))?;
+
+    // Makes the compiler rebuild the crate when the config file changes.
+    let filepath = filepath.display().to_string();
+
+    Ok(quote! {
+        const _: &[u8] = include_bytes!(#filepath);
+
+        #tokens
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(tokens: TokenStream) -> syn::Result<IncludeConfig> {
+        syn::parse2(tokens)
+    }
+
+    #[test]
+    fn path_only() {
+        let input = parse(quote!("config.toml")).unwrap();
+
+        assert_eq!(input.path.value(), "config.toml");
+        assert_eq!(input.options, StructOptions::default());
+    }
+
+    #[test]
+    fn all_settings() {
+        let input = parse(quote! {
+            "config.toml",
+            format = Toml,
+            struct_name = "Settings",
+            const_name = "SETTINGS",
+            generate_const = false,
+            derived_traits = ["Debug"],
+            serde_support = Mixed { serialize: false, deserialize: true },
+            use_serde_derive_crate = true,
+            generate_load_fns = false,
+            dynamic_loading = Never,
+            env_prefix = "APP",
+            default_float_size = F32,
+            default_int_size = I32,
+            max_array_size = 4,
+            preserve_key_order = true,
+            deduplicate_structs = true,
+            report_all_errors = true,
+            sanitize_keys = true,
+            raw_identifiers = true,
+            edition = Rust2018,
+            struct_naming = PascalCaseKey,
+            struct_name_overrides = { "database.pool": "Pool" },
+        })
+        .unwrap();
+
+        assert_eq!(
+            input.options,
+            StructOptions {
+                format: Some(Format::Toml),
+                struct_name: "Settings".to_owned(),
+                const_name: Some("SETTINGS".to_owned()),
+                generate_const: false,
+                derived_traits: vec!["Debug".to_owned()],
+                serde_support: SerdeSupport::Mixed {
+                    serialize: false,
+                    deserialize: true
+                },
+                use_serde_derive_crate: true,
+                generate_load_fns: false,
+                dynamic_loading: DynamicLoading::Never,
+                env_prefix: Some("APP".to_owned()),
+                default_float_size: FloatSize::F32,
+                default_int_size: IntSize::I32,
+                max_array_size: 4,
+                preserve_key_order: true,
+                deduplicate_structs: true,
+                report_all_errors: true,
+                sanitize_keys: true,
+                raw_identifiers: true,
+                edition: Edition::Rust2018,
+                struct_naming: StructNaming::PascalCaseKey,
+                struct_name_overrides: [("database.pool".to_owned(), "Pool".to_owned())]
+                    .into_iter()
+                    .collect(),
+                ..StructOptions::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let error = |tokens| parse(tokens).err().unwrap().to_string();
+
+        assert_eq!(
+            error(quote!("config.toml", colour = "red")),
+            "unknown setting `colour`"
+        );
+        assert_eq!(
+            error(quote!(
+                "config.toml",
+                generate_const = true,
+                generate_const = false
+            )),
+            "duplicate setting `generate_const`"
+        );
+        assert_eq!(
+            error(quote!("config.toml", dynamic_loading = Sometimes)),
+            "expected one of `Always`, `DebugOnly`, `Never`"
+        );
+        assert_eq!(
+            error(quote!("config.toml", serde_support = Mixed { both: true })),
+            "expected `serialize` or `deserialize`"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! Checks that the generated types of nested arrays match their values, by
+//! compiling them.
+
+mod fixed {
+    config_struct_macros::include_config!(
+        "tests/fixtures/fixed_arrays.json",
+        generate_load_fns = false,
+        max_array_size = 4,
+        struct_name = "Fixed",
+    );
+}
+
+mod slices {
+    config_struct_macros::include_config!(
+        "tests/fixtures/arrays.json",
+        generate_load_fns = false,
+        struct_name = "Slices",
+    );
+}
+
+#[test]
+fn nested_arrays() {
+    let grid: &[[i64; 2]; 2] = &fixed::FIXED.grid;
+    assert_eq!(grid, &[[1, 2], [3, 4]]);
+    let ports: &[i64; 2] = &fixed::FIXED.servers[1].ports;
+    assert_eq!(ports, &[8080, 8443]);
+
+    assert_eq!(slices::SLICES.ragged.len(), 2);
+    assert_eq!(&*slices::SLICES.ragged[1], &[3]);
+    assert_eq!(&*slices::SLICES.servers[1].ports, &[8080]);
+}
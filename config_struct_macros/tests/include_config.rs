@@ -0,0 +1,44 @@
+//! Checks that `include_config!` expands to items which compile, with the
+//! default options and with the naming settings.
+
+mod defaults {
+    config_struct_macros::include_config!("tests/fixtures/app.toml");
+}
+
+// Two configs in one module only compile if none of their structs share a
+// name.
+mod settings {
+    config_struct_macros::include_config!(
+        "tests/fixtures/app.toml",
+        struct_name = "App",
+        derived_traits = ["Debug", "Clone", "PartialEq"],
+    );
+
+    config_struct_macros::include_config!(
+        "tests/fixtures/app.toml",
+        struct_name = "Fallback",
+        const_name = "FALLBACK_APP",
+        struct_naming = PascalCaseKeyPath,
+        struct_name_overrides = { "database.pool": "FallbackPool" },
+    );
+}
+
+#[test]
+fn default_options() {
+    let config: &defaults::Config = &defaults::CONFIG;
+    let pool: &defaults::_Config__database__pool = &config.database.pool;
+    assert_eq!(config.name, "app");
+    assert_eq!(&*config.tags, &["a", "b"]);
+    assert_eq!(pool.size, 4);
+}
+
+#[test]
+fn naming_settings() {
+    let app: &settings::_App__database = &settings::APP.database;
+    assert_eq!(app.clone(), settings::APP.database);
+
+    let fallback: &settings::Database = &settings::FALLBACK_APP.database;
+    let pool: &settings::FallbackPool = &fallback.pool;
+    assert_eq!(fallback.host, app.host);
+    assert_eq!(pool.size, app.pool.size);
+}
@@ -0,0 +1,22 @@
+//! Checks that two configs with nested tables can be included in the same
+//! module, since their nested structs are named after their own structs.
+
+config_struct_macros::include_config!(
+    "tests/fixtures/service.toml",
+    generate_load_fns = false,
+    struct_name = "Primary",
+);
+
+config_struct_macros::include_config!(
+    "tests/fixtures/service.toml",
+    generate_load_fns = false,
+    struct_name = "Replica",
+);
+
+#[test]
+fn nested_structs_are_named_per_config() {
+    let primary: &_Primary__database = &PRIMARY.database;
+    let replica: &_Replica__database = &REPLICA.database;
+    assert_eq!(primary.port, replica.port);
+    assert_eq!(PRIMARY.name, "service");
+}
@@ -0,0 +1,502 @@
+use std::collections::BTreeMap;
+
+use super::value::{GenericStruct, GenericValue};
+
+/// The comments written in a config file, to be used as documentation.
+#[derive(Debug, Default, PartialEq)]
+pub struct Comments {
+    /// The comment at the top of the file, when it is separated from the
+    /// first key by a blank line.
+    pub file: Option<String>,
+
+    /// The comment directly above each key, by key path. Keys inside arrays
+    /// of tables share the path of the array.
+    pub keys: BTreeMap<Vec<String>, String>,
+}
+
+impl Comments {
+    /// Attaches the comments to the struct as docs: the file comment for the
+    /// struct itself, and the key comments for its fields and the fields of
+    /// every struct nested inside it.
+    pub fn apply(self, struct_value: &mut GenericStruct) {
+        struct_value.doc = self.file;
+        apply_to_struct(&self.keys, &mut Vec::new(), struct_value);
+    }
+}
+
+fn apply_to_struct(
+    comments: &BTreeMap<Vec<String>, String>,
+    path: &mut Vec<String>,
+    struct_value: &mut GenericStruct,
+) {
+    for (key, value) in &mut struct_value.fields {
+        path.push(key.clone());
+        if let Some(comment) = comments.get(path) {
+            struct_value.field_docs.insert(key.clone(), comment.clone());
+        }
+        apply_to_value(comments, path, value);
+        path.pop();
+    }
+}
+
+fn apply_to_value(
+    comments: &BTreeMap<Vec<String>, String>,
+    path: &mut Vec<String>,
+    value: &mut GenericValue,
+) {
+    match value {
+        GenericValue::Struct(struct_value) => apply_to_struct(comments, path, struct_value),
+        GenericValue::Array(values) => {
+            for value in values {
+                apply_to_value(comments, path, value);
+            }
+        }
+        GenericValue::Option(Some(value)) => apply_to_value(comments, path, value),
+        _ => (),
+    }
+}
+
+/// Collects consecutive comment lines until they are attached to a key or
+/// separated from the next one by a blank line.
+#[derive(Default)]
+struct CommentCollector {
+    comments: Comments,
+    pending: Vec<String>,
+    seen_key: bool,
+}
+
+impl CommentCollector {
+    fn comment(&mut self, text: &str) {
+        self.pending.push(text.to_owned());
+    }
+
+    fn blank(&mut self) {
+        if !self.seen_key && self.comments.file.is_none() && !self.pending.is_empty() {
+            self.comments.file = Some(self.pending.join("\n"));
+        }
+        self.pending.clear();
+    }
+
+    fn key(&mut self, path: Vec<String>) {
+        self.seen_key = true;
+        if !self.pending.is_empty() {
+            let comment = self.pending.join("\n");
+            self.comments.keys.entry(path).or_insert(comment);
+            self.pending.clear();
+        }
+    }
+
+    fn other(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Reads the comments from a TOML file.
+#[cfg(feature = "toml-parsing")]
+pub fn toml_comments(toml: &str) -> Comments {
+    let mut collector = CommentCollector::default();
+    let mut table = Vec::new();
+    let mut multiline_string = None;
+
+    for line in toml.lines() {
+        let line = line.trim();
+
+        // Lines inside a multi-line string are part of its value.
+        if let Some(delimiter) = multiline_string {
+            if let Some(end) = closing_delimiter(line, delimiter) {
+                multiline_string = unclosed_multiline_string(&line[end + delimiter.len()..]);
+            }
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            collector.comment(comment);
+            continue;
+        } else if line.is_empty() {
+            collector.blank();
+            continue;
+        } else if line.starts_with('[') {
+            let header = line.trim_start_matches('[');
+            match parse_toml_key(header) {
+                Some((path, rest)) if rest.starts_with(']') => {
+                    collector.key(path.clone());
+                    table = path;
+                }
+                _ => collector.other(),
+            }
+        } else {
+            match parse_toml_key(line) {
+                Some((key, rest)) if rest.starts_with('=') => {
+                    let mut path = table.clone();
+                    path.extend(key);
+                    collector.key(path);
+                }
+                _ => collector.other(),
+            }
+        }
+
+        multiline_string = unclosed_multiline_string(line);
+    }
+
+    collector.comments
+}
+
+/// Parses a dotted TOML key, returning its parts and the rest of the line.
+#[cfg(feature = "toml-parsing")]
+fn parse_toml_key(line: &str) -> Option<(Vec<String>, &str)> {
+    let mut parts = Vec::new();
+    let mut rest = line.trim_start();
+
+    loop {
+        let (part, after) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = closing_quote(quoted)?;
+            (quoted[..end].replace("\\\"", "\""), &quoted[end + 1..])
+        } else if let Some(quoted) = rest.strip_prefix('\'') {
+            let end = quoted.find('\'')?;
+            (quoted[..end].to_owned(), &quoted[end + 1..])
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            (rest[..end].to_owned(), &rest[end..])
+        };
+
+        parts.push(part);
+        rest = after.trim_start();
+
+        match rest.strip_prefix('.') {
+            Some(after) => rest = after.trim_start(),
+            None => return Some((parts, rest)),
+        }
+    }
+}
+
+#[cfg(feature = "toml-parsing")]
+fn closing_quote(quoted: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in quoted.char_indices() {
+        match c {
+            '"' if !escaped => return Some(index),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// The delimiter of a multi-line string which is opened on a line of TOML
+/// but not closed on it, if any.
+#[cfg(feature = "toml-parsing")]
+fn unclosed_multiline_string(line: &str) -> Option<&'static str> {
+    let mut rest = line;
+
+    loop {
+        rest = &rest[rest.find(['"', '\'', '#'])?..];
+
+        if let Some(delimiter) = ["\"\"\"", "'''"]
+            .into_iter()
+            .find(|delimiter| rest.starts_with(delimiter))
+        {
+            let quoted = &rest[delimiter.len()..];
+            match closing_delimiter(quoted, delimiter) {
+                Some(end) => rest = &quoted[end + delimiter.len()..],
+                None => return Some(delimiter),
+            }
+        } else if let Some(quoted) = rest.strip_prefix('"') {
+            rest = &quoted[closing_quote(quoted)? + 1..];
+        } else if let Some(quoted) = rest.strip_prefix('\'') {
+            rest = &quoted[quoted.find('\'')? + 1..];
+        } else {
+            // The rest of the line is a comment.
+            return None;
+        }
+    }
+}
+
+/// The index of the delimiter which closes a multi-line string.
+#[cfg(feature = "toml-parsing")]
+fn closing_delimiter(quoted: &str, delimiter: &str) -> Option<usize> {
+    let quote = delimiter.chars().next()?;
+    let mut escaped = false;
+
+    for (index, c) in quoted.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if quoted[index..].starts_with(delimiter) {
+            // Up to two quotes can end the string just before its delimiter.
+            let extra = quoted[index + delimiter.len()..]
+                .chars()
+                .take(2)
+                .take_while(|&c| c == quote)
+                .count();
+            return Some(index + extra);
+        }
+    }
+    None
+}
+
+/// Reads the comments from a YAML file.
+#[cfg(feature = "yaml-parsing")]
+pub fn yaml_comments(yaml: &str) -> Comments {
+    let mut collector = CommentCollector::default();
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut block_scalar_indent = None;
+    let mut quoted_scalar = None;
+
+    for line in yaml.lines() {
+        let content = line.trim_start();
+        let mut indent = line.len() - content.len();
+
+        // Lines inside a block scalar or a multi-line quoted scalar are part
+        // of its value.
+        if let Some(block_indent) = block_scalar_indent {
+            if content.is_empty() || indent > block_indent {
+                continue;
+            }
+            block_scalar_indent = None;
+        }
+        if let Some(quote) = quoted_scalar {
+            if closing_yaml_quote(content, quote).is_some() {
+                quoted_scalar = None;
+            }
+            continue;
+        }
+
+        if let Some(comment) = content.strip_prefix('#') {
+            collector.comment(comment);
+            continue;
+        } else if content.is_empty() {
+            collector.blank();
+            continue;
+        } else if content.starts_with("---") || content.starts_with("...") {
+            collector.other();
+            continue;
+        }
+
+        // The keys of a mapping inside a sequence are indented past the dash.
+        let mut content = content;
+        let mut node_indent = indent;
+        while let Some(item) = content.strip_prefix('-') {
+            if !(item.is_empty() || item.starts_with(' ')) {
+                break;
+            }
+            let item_content = item.trim_start();
+            node_indent = indent;
+            indent += content.len() - item_content.len();
+            content = item_content;
+        }
+
+        let value = match parse_yaml_key(content) {
+            Some((key, value)) => {
+                while parents.last().is_some_and(|(parent, _)| *parent >= indent) {
+                    parents.pop();
+                }
+
+                let mut path: Vec<String> = parents.iter().map(|(_, key)| key.clone()).collect();
+                path.push(key.clone());
+                collector.key(path);
+
+                parents.push((indent, key));
+                node_indent = indent;
+                value
+            }
+            None => {
+                collector.other();
+                content
+            }
+        };
+
+        if value.starts_with('|') || value.starts_with('>') {
+            block_scalar_indent = Some(node_indent);
+        } else if let Some(quote) = value.chars().next().filter(|&c| c == '"' || c == '\'') {
+            if closing_yaml_quote(&value[1..], quote).is_none() {
+                quoted_scalar = Some(quote);
+            }
+        }
+    }
+
+    collector.comments
+}
+
+/// The index of the quote which closes a quoted YAML scalar.
+#[cfg(feature = "yaml-parsing")]
+fn closing_yaml_quote(quoted: &str, quote: char) -> Option<usize> {
+    let mut chars = quoted.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        if c == '\\' && quote == '"' {
+            chars.next();
+        } else if c == quote {
+            // Single quotes are escaped by doubling them.
+            if quote == '\'' && chars.peek().is_some_and(|&(_, next)| next == '\'') {
+                chars.next();
+            } else {
+                return Some(index);
+            }
+        }
+    }
+    None
+}
+
+/// Parses a YAML mapping key, returning it and the value after the colon.
+#[cfg(feature = "yaml-parsing")]
+fn parse_yaml_key(content: &str) -> Option<(String, &str)> {
+    let (key, rest) = if let Some(quoted) = content.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        (quoted[..end].to_owned(), &quoted[end + 1..])
+    } else if let Some(quoted) = content.strip_prefix('\'') {
+        let end = quoted.find('\'')?;
+        (quoted[..end].to_owned(), &quoted[end + 1..])
+    } else {
+        let end = content
+            .match_indices(':')
+            .map(|(index, _)| index)
+            .find(|&index| {
+                let after = &content[index + 1..];
+                after.is_empty() || after.starts_with(' ')
+            })?;
+        (content[..end].trim_end().to_owned(), &content[end..])
+    };
+
+    let value = rest.trim_start().strip_prefix(':')?;
+    if !(value.is_empty() || value.starts_with(' ')) || key.is_empty() {
+        return None;
+    }
+    Some((key, value.trim_start()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(path: &str) -> Vec<String> {
+        path.split('.').map(str::to_owned).collect()
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn toml() {
+        let comments = toml_comments(
+            r#"# The application config.
+#
+# Edit with care.
+
+# The name of the app.
+name = "app"
+# Not a doc comment, because of the blank line.
+
+debug = false
+text = """
+# Not a comment.
+'''
+"""
+literal = '''
+# Not a comment either.
+"""
+'''
+quoted = """ends with quotes"""""
+escaped = """\"""
+# Not a comment, because the quotes were escaped.
+"""
+list = [
+    """
+# Nor this.
+""", '''
+# Nor this.
+''']
+# The version.
+version = 2
+
+# Connection settings.
+[database]
+# The server address.
+"host" = "localhost"
+pool.size = 4 # Not a doc comment either.
+
+# One entry per server.
+[[servers]]
+# The port to listen on.
+port = 80
+[[servers]]
+# Ignored in favour of the first comment.
+port = 81
+"#,
+        );
+
+        assert_eq!(
+            comments.file.as_deref(),
+            Some(" The application config.\n\n Edit with care.")
+        );
+        assert_eq!(
+            comments.keys,
+            [
+                (path("name"), " The name of the app."),
+                (path("version"), " The version."),
+                (path("database"), " Connection settings."),
+                (path("database.host"), " The server address."),
+                (path("servers"), " One entry per server."),
+                (path("servers.port"), " The port to listen on."),
+            ]
+            .into_iter()
+            .map(|(path, comment)| (path, comment.to_owned()))
+            .collect()
+        );
+    }
+
+    #[cfg(feature = "yaml-parsing")]
+    #[test]
+    fn yaml() {
+        let comments = yaml_comments(
+            r#"# The name of the app.
+name: app
+text: |
+  # Not a comment.
+  key: value
+# Connection settings.
+database:
+  # The server address.
+  "host": localhost
+servers:
+  # The port to listen on.
+  - port: 80
+    # Whether to use TLS.
+    tls: true
+# After a nested mapping.
+debug: false
+items:
+  - |
+    # Not a comment.
+  - "multi-line
+    # Not a comment either.
+    string"
+  - 'it''s
+    # Nor this.
+    quoted'
+# The version.
+version: 2
+"#,
+        );
+
+        assert_eq!(comments.file, None);
+        assert_eq!(
+            comments.keys,
+            [
+                (path("name"), " The name of the app."),
+                (path("database"), " Connection settings."),
+                (path("database.host"), " The server address."),
+                (path("servers.port"), " The port to listen on."),
+                (path("servers.tls"), " Whether to use TLS."),
+                (path("debug"), " After a nested mapping."),
+                (path("version"), " The version."),
+            ]
+            .into_iter()
+            .map(|(path, comment)| (path, comment.to_owned()))
+            .collect()
+        );
+    }
+}
@@ -1,82 +1,499 @@
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
 use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
 
-use failure::Fail;
+use super::options::SynchronizationScope;
 
 /// An error type for errors while generating config struct modules.
 ///
 /// Errors can either occur during IO (when reading or creating files) or during
 /// the generation itself.
-#[derive(Debug, Fail)]
+#[derive(Debug)]
 pub enum Error {
-    #[fail(display = "Generation error: {}", _0)]
-    Generation(#[cause] GenerationError),
+    Generation(GenerationError),
 
-    #[fail(display = "IO error: {}", _0)]
-    IO(#[cause] IOError),
+    IO(IOError),
+
+    /// Occurs when writing to `OUT_DIR` outside of a build script.
+    MissingOutDir,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::Generation(error) => write!(f, "Generation error: {error}"),
+            Error::IO(error) => write!(f, "IO error: {error}"),
+            Error::MissingOutDir => write!(
+                f,
+                "The OUT_DIR environment variable is not set. (Is this running in a build script?)"
+            ),
+        }
+    }
 }
 
+// The messages of wrapped errors are already part of the messages of these
+// errors, so they are not returned again as sources.
+impl StdError for Error {}
+
 /// An error occurring during code generation.
-#[derive(Debug, Fail)]
+#[derive(Debug)]
 pub enum GenerationError {
     /// Occurs when the config format can't be determined from the
     /// filename extension of the input file.
-    #[fail(
-        display = "Unknown input format: `{}`. (Maybe you need to enable the right feature?)",
-        _0
-    )]
     UnknownInputFormat(String),
 
     /// Occurs when encountering a field in the config which is not a
     /// valid name for a struct field.
-    #[fail(display = "Invalid field name: `{}`.", _0)]
     InvalidFieldName(String),
 
     /// Occurs when encountering a field in the config which is not a
     /// valid name for an enum variant.
-    #[fail(display = "Invalid variant name: `{}`.", _0)]
     InvalidVariantName(String),
 
+    /// Occurs when two different entries would produce the same enum
+    /// variant name.
+    DuplicateVariantName(String),
+
     /// Occurs when an array in the config file contains multiple different types
     /// of data, which cannot be represented in a Rust struct.
-    #[fail(
-        display = "Array under key `{}` has elements of different types. Arrays must be homogenous.",
-        _0
-    )]
     HeterogenousArray(String),
 
+    /// Occurs when fixed-size arrays are enabled, if arrays nested at the
+    /// same position in the elements of an array have different lengths.
+    MismatchedArrayLengths(String),
+
+    /// Occurs when two nested structs with different fields would be given
+    /// the same name.
+    DuplicateStructName(String),
+
+    /// Occurs when generating an enum with a data struct, if the data under
+    /// each key of the config does not all have the same type.
+    HeterogenousVariantData,
+
     /// Occurs when generating from source and not a file, if attempting to also
     /// generate dynamic loading functions.
     ///
     /// Because no input filepath was given, it's impossible to generate a function
     /// which loads from that file.
-    #[fail(
-        display = "Cannot generate dynamic loading functions without a filename.
-(Generate struct from a file, set generate_load_fns: false, or set dynamic_loading: DynamicLoading::Never to fix.)"
-    )]
     MissingFilePath,
 
+    /// Occurs when generating from layered config files, if the list of
+    /// files is empty.
+    MissingLayers,
+
+    /// Occurs when generating from layered config files, if the format of
+    /// a layer differs from the format of the first layer.
+    MismatchedLayerFormat(String),
+
+    /// Occurs when a map in the config has a key which is not a string,
+    /// and so cannot be the name of a struct field.
+    NonStringKey {
+        /// The key path of the map.
+        key_path: String,
+        /// The key, written in the format of the config.
+        key: String,
+    },
+
+    /// Occurs when a number in the config cannot be represented by any of
+    /// the Rust number types.
+    UnsupportedNumber {
+        /// The key path of the number.
+        key_path: String,
+        /// The number, written in the format of the config.
+        number: String,
+    },
+
+    /// Occurs when the config contains a kind of value which cannot be
+    /// represented in a Rust struct.
+    UnsupportedValue {
+        /// The key path of the value.
+        key_path: String,
+        /// A description of the value.
+        kind: String,
+    },
+
+    /// Occurs when two keys of the same map in the config would produce
+    /// the same struct field name.
+    DuplicateFieldName {
+        /// The key path of the map.
+        key_path: String,
+        /// The key which produced the field name first.
+        first_key: String,
+        /// The key which produced it again.
+        second_key: String,
+        /// The field name produced by both keys.
+        field_name: String,
+    },
+
+    /// Occurs when a flighting file does not have the expected structure,
+    /// or is not preceded by a config layer to patch.
+    InvalidFlighting(String),
+
+    /// Occurs when a flighting file overrides a setting which is not in the
+    /// base config.
+    UnknownFlightingSetting(String),
+
+    /// Occurs when a flighting file overrides a setting with a value of a
+    /// different type, which would change the generated struct.
+    MismatchedFlightingSetting(String),
+
+    /// Occurs when generating dynamic loading functions from layers which
+    /// include a flighting file, because flighting is only applied when the
+    /// struct is generated.
+    DynamicFlighting,
+
     /// Occurs when the config file could not be correctly parsed.
-    #[fail(display = "Deserialization failed: {}", _0)]
-    DeserializationFailed(String),
+    DeserializationFailed(DeserializationError),
 
     /// Occurs when invalid options were provided.
-    #[fail(display = "Invalid options error: {}", _0)]
-    StructOptions(#[cause] OptionsError),
+    StructOptions(OptionsError),
+
+    /// Occurs when the config has problems which prevent generating a
+    /// struct from it, if `report_all_errors` is set.
+    InvalidConfig(ValidationReport),
+}
+
+impl GenerationError {
+    /// Records the file that the config was read from, if this error points
+    /// at a location in it.
+    pub(crate) fn in_file(mut self, filepath: Option<&Path>) -> Self {
+        if let GenerationError::DeserializationFailed(ref mut error) = self {
+            if let (Some(location), Some(filepath)) = (&mut error.location, filepath) {
+                location.filepath = Some(filepath.to_owned());
+            }
+        }
+        self
+    }
+}
+
+impl Display for GenerationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            GenerationError::UnknownInputFormat(format) => write!(
+                f,
+                "Unknown input format: `{format}`. (Maybe you need to enable the right feature?)"
+            ),
+            GenerationError::InvalidFieldName(name) => write!(f, "Invalid field name: `{name}`."),
+            GenerationError::InvalidVariantName(name) => {
+                write!(f, "Invalid variant name: `{name}`.")
+            }
+            GenerationError::DuplicateVariantName(name) => {
+                write!(f, "Duplicate variant name: `{name}`.")
+            }
+            GenerationError::HeterogenousArray(key) => write!(
+                f,
+                "Array under key `{key}` has elements of different types. Arrays must be homogenous."
+            ),
+            GenerationError::MismatchedArrayLengths(key) => write!(
+                f,
+                "Arrays nested in the array under key `{key}` have different lengths, so they cannot be fixed-size arrays. (Set `max_array_size` to 0 to use slices.)"
+            ),
+            GenerationError::DuplicateStructName(name) => write!(
+                f,
+                "Two different structs would be named `{name}`. (Change the naming options to fix.)"
+            ),
+            GenerationError::HeterogenousVariantData => write!(
+                f,
+                "Enum variants have data of different types. Variant data must be homogenous."
+            ),
+            GenerationError::MissingFilePath => write!(
+                f,
+                "Cannot generate dynamic loading functions without a filename.
+(Generate struct from a file, set generate_load_fns: false, or set dynamic_loading: DynamicLoading::Never to fix.)"
+            ),
+            GenerationError::MissingLayers => write!(
+                f,
+                "Cannot generate structs from an empty list of config layers."
+            ),
+            GenerationError::MismatchedLayerFormat(layer) => write!(
+                f,
+                "Config layer `{layer}` is in a different format to the first layer. All layers must be in the same format."
+            ),
+            GenerationError::NonStringKey { key_path, key } => write!(
+                f,
+                "The map at {} has the key `{key}`, which is not a string. Keys must be strings.",
+                describe_key_path(key_path)
+            ),
+            GenerationError::UnsupportedNumber { key_path, number } => write!(
+                f,
+                "The number `{number}` at {} cannot be represented by any Rust number type.",
+                describe_key_path(key_path)
+            ),
+            GenerationError::UnsupportedValue { key_path, kind } => write!(
+                f,
+                "Unsupported value at {}: {kind}.",
+                describe_key_path(key_path)
+            ),
+            GenerationError::DuplicateFieldName {
+                key_path,
+                first_key,
+                second_key,
+                field_name,
+            } => write!(
+                f,
+                "The keys `{first_key}` and `{second_key}` of the map at {} would both become the field `{field_name}`.",
+                describe_key_path(key_path)
+            ),
+            GenerationError::InvalidFlighting(message) => {
+                write!(f, "Invalid flighting file: {message}.")
+            }
+            GenerationError::UnknownFlightingSetting(path) => {
+                write!(f, "Flighting setting `{path}` is not in the base config.")
+            }
+            GenerationError::MismatchedFlightingSetting(path) => write!(
+                f,
+                "Flighting setting `{path}` has a different type to the base config."
+            ),
+            GenerationError::DynamicFlighting => write!(
+                f,
+                "Cannot generate dynamic loading functions for a config with a flighting file.
+(Set generate_load_fns: false or dynamic_loading: DynamicLoading::Never to fix.)"
+            ),
+            GenerationError::DeserializationFailed(error) => {
+                write!(f, "Deserialization failed: {error}")
+            }
+            GenerationError::StructOptions(error) => write!(f, "Invalid options error: {error}"),
+            GenerationError::InvalidConfig(report) => write!(f, "{report}"),
+        }
+    }
+}
+
+fn describe_key_path(key_path: &str) -> String {
+    if key_path.is_empty() {
+        "the root of the config".to_owned()
+    } else {
+        format!("`{key_path}`")
+    }
+}
+
+impl StdError for GenerationError {}
+
+/// Every problem found while validating a config.
+#[derive(Debug)]
+pub struct ValidationReport {
+    /// The problems, in the order their keys appear in the struct.
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-/// An error type for when a [`StructOptions`](struct.StructOptions.html) value
-/// failed validation.
-#[derive(Debug, Fail)]
+/// A problem found while validating a config.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// The path to the value with the problem, such as `servers[3].ports`.
+    pub key_path: String,
+
+    /// The problem.
+    pub error: GenerationError,
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.diagnostics.len() {
+            1 => write!(f, "Found 1 problem in the config:")?,
+            count => write!(f, "Found {count} problems in the config:")?,
+        }
+        for diagnostic in &self.diagnostics {
+            write!(f, "\n  {}: {}", diagnostic.key_path, diagnostic.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for ValidationReport {}
+
+/// An error type for when a config could not be parsed, pointing at the
+/// part of the config which caused it where possible.
+#[derive(Debug)]
+pub struct DeserializationError {
+    /// A description of the problem.
+    pub message: String,
+
+    /// Where in the config the problem is, if known.
+    pub location: Option<SourceLocation>,
+}
+
+/// A location in a config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The file the config was read from, if it was read from a file.
+    pub filepath: Option<PathBuf>,
+
+    /// The line number, starting from 1.
+    pub line: usize,
+
+    /// The column number, starting from 1.
+    pub column: usize,
+
+    /// The text of the line.
+    pub snippet: String,
+}
+
+impl DeserializationError {
+    /// An error from the parser of a config format, with the line and column
+    /// (both starting from 1) that the parser reported for it, if any.
+    pub(crate) fn from_parser<E: Display>(
+        error: E,
+        config: &str,
+        line_column: Option<(usize, usize)>,
+    ) -> Self {
+        let location = line_column
+            .filter(|&(line, _)| line > 0)
+            .map(|(line, column)| SourceLocation {
+                filepath: None,
+                line,
+                column: column.max(1),
+                snippet: config.lines().nth(line - 1).unwrap_or("").to_owned(),
+            });
+
+        DeserializationError {
+            message: error.to_string(),
+            location,
+        }
+    }
+}
+
+impl Display for DeserializationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if let Some(location) = &self.location {
+            let line_number = location.line.to_string();
+            let gutter = " ".repeat(line_number.len());
+            // Keep any tabs so that the caret lines up with the snippet.
+            let indent: String = location
+                .snippet
+                .chars()
+                .take(location.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            match &location.filepath {
+                Some(filepath) => write!(
+                    f,
+                    "\n{gutter}--> {}:{}:{}",
+                    filepath.display(),
+                    location.line,
+                    location.column
+                )?,
+                None => write!(
+                    f,
+                    "\n{gutter}--> line {}, column {}",
+                    location.line, location.column
+                )?,
+            }
+            write!(
+                f,
+                "\n{gutter} |\n{line_number} | {}\n{gutter} | {indent}^",
+                location.snippet
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl StdError for DeserializationError {}
+
+/// An error type for when a [`StructOptions`](struct.StructOptions.html) or
+/// [`EnumOptions`](struct.EnumOptions.html) value failed validation.
+#[derive(Debug)]
 pub enum OptionsError {
     /// Occurs when the provided `struct_name` is not a valid Rust identifier.
-    #[fail(display = "Invalid name for a struct: `{}`.", _0)]
     InvalidStructName(String),
 
     /// Occurs when the provided `const_name` is not a valid Rust identifier.
-    #[fail(display = "Invalid name for a const: `{}`.", _0)]
     InvalidConstName(String),
+
+    /// Occurs when the provided `enum_name` is not a valid Rust identifier.
+    InvalidEnumName(String),
+
+    /// Occurs when the provided `all_variants_const` is not a valid Rust identifier.
+    InvalidAllVariantsConstName(String),
+
+    /// Occurs when one of the provided `derived_traits` is not a valid Rust path.
+    InvalidDerivedTrait(String),
+
+    /// Occurs when `impl_from_str` is set but `all_variants_const` is `None`.
+    FromStrRequiresAllVariantsConst,
+
+    /// Occurs when `env_prefix` is set but `derived_traits` does not include
+    /// `Clone`, which applying the overrides to a loaded config needs.
+    EnvPrefixRequiresClone,
+
+    /// Occurs when the provided `data_struct_name` is the same as the
+    /// `enum_name`, so the two types would conflict.
+    DataStructNameConflict(String),
+}
+
+impl Display for OptionsError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            OptionsError::InvalidStructName(name) => {
+                write!(f, "Invalid name for a struct: `{name}`.")
+            }
+            OptionsError::InvalidConstName(name) => {
+                write!(f, "Invalid name for a const: `{name}`.")
+            }
+            OptionsError::InvalidEnumName(name) => write!(f, "Invalid name for an enum: `{name}`."),
+            OptionsError::InvalidAllVariantsConstName(name) => {
+                write!(f, "Invalid name for the all-variants const: `{name}`.")
+            }
+            OptionsError::InvalidDerivedTrait(path) => {
+                write!(f, "Invalid path for a derived trait: `{path}`.")
+            }
+            OptionsError::FromStrRequiresAllVariantsConst => write!(
+                f,
+                "The `impl_from_str` option requires `all_variants_const` to be set."
+            ),
+            OptionsError::EnvPrefixRequiresClone => write!(
+                f,
+                "The `env_prefix` option requires `Clone` to be in `derived_traits`."
+            ),
+            OptionsError::DataStructNameConflict(name) => write!(
+                f,
+                "The `data_struct_name` and `enum_name` options are both `{name}`."
+            ),
+        }
+    }
 }
 
+impl StdError for OptionsError {}
+
+/// An error type for when the synchronization file of a
+/// [`ConfigParserOptions`](struct.ConfigParserOptions.html) could not be
+/// resolved or created.
+#[derive(Debug)]
+pub enum SynchronizationError {
+    /// Occurs when the `app_name` is empty or contains characters which are
+    /// not safe to use in a path.
+    InvalidAppName(String),
+
+    /// Occurs when no base directory was configured for the scope, and the
+    /// default one could not be determined from the environment.
+    MissingBaseDirectory(SynchronizationScope),
+
+    /// Occurs when the synchronization file could not be created.
+    IO(IOError),
+}
+
+impl Display for SynchronizationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SynchronizationError::InvalidAppName(name) => write!(
+                f,
+                "Invalid app name: `{name}`. (Only ASCII letters, digits, `_`, `-` and `.` are allowed.)"
+            ),
+            SynchronizationError::MissingBaseDirectory(scope) => write!(
+                f,
+                "Cannot determine the synchronization directory for the {scope:?} scope. (Set it in the options to fix.)"
+            ),
+            SynchronizationError::IO(error) => write!(f, "IO error: {error}"),
+        }
+    }
+}
+
+impl StdError for SynchronizationError {}
+
 impl From<GenerationError> for Error {
     fn from(error: GenerationError) -> Self {
         Error::Generation(error)
@@ -94,3 +511,50 @@ impl From<OptionsError> for GenerationError {
         GenerationError::StructOptions(error)
     }
 }
+
+impl From<DeserializationError> for GenerationError {
+    fn from(error: DeserializationError) -> Self {
+        GenerationError::DeserializationFailed(error)
+    }
+}
+
+impl From<IOError> for SynchronizationError {
+    fn from(error: IOError) -> Self {
+        SynchronizationError::IO(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet() {
+        let config = "name = 'app'\n\tport = = 80\n";
+        let error = DeserializationError::from_parser(
+            IOError::other("expected a value"),
+            config,
+            Some((2, 9)),
+        );
+        let error = GenerationError::from(error).in_file(Some(Path::new("config.toml")));
+
+        assert_eq!(
+            error.to_string(),
+            "Deserialization failed: expected a value
+ --> config.toml:2:9
+  |
+2 | \tport = = 80
+  | \t       ^"
+        );
+
+        // The parser's message is only reported once along the error chain.
+        let error = Error::from(error);
+        let mut messages = vec![error.to_string()];
+        let mut source = error.source();
+        while let Some(error) = source {
+            messages.push(error.to_string());
+            source = error.source();
+        }
+        assert_eq!(messages.concat().matches("expected a value").count(), 1);
+    }
+}
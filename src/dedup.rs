@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::generation::type_string;
+use super::options::StructOptions;
+use super::parsing;
+use super::value::{GenericStruct, GenericValue};
+
+/// A nested struct found while walking the config, identified by its
+/// original name and the key path leading to it.
+struct Member {
+    struct_name: String,
+    path: Vec<String>,
+}
+
+/// Renames structurally identical nested structs so that they all share
+/// one struct type.
+pub fn deduplicate_structs(root: &mut GenericStruct, options: &StructOptions) {
+    let mut shapes: BTreeMap<String, Vec<Member>> = BTreeMap::new();
+    let mut all_names = BTreeSet::new();
+    for (key, value) in &root.fields {
+        collect_shapes(value, &mut vec![key.clone()], &mut shapes, &mut all_names);
+    }
+
+    // A shared name must not be taken by the root struct, any other nested
+    // struct, or the shared struct of another shape.
+    all_names.insert(root.struct_name.clone());
+
+    let mut shared_names = BTreeMap::new();
+    for members in shapes.values() {
+        if members.len() > 1 {
+            let shared_name = shared_name(members, &all_names, options);
+            all_names.insert(shared_name.clone());
+            for member in members {
+                shared_names.insert(member.struct_name.clone(), shared_name.clone());
+            }
+        }
+    }
+
+    for (_, value) in &mut root.fields {
+        rename_shared(value, &shared_names);
+    }
+}
+
+fn collect_shapes(
+    value: &GenericValue,
+    path: &mut Vec<String>,
+    shapes: &mut BTreeMap<String, Vec<Member>>,
+    all_names: &mut BTreeSet<String>,
+) {
+    match value {
+        GenericValue::Struct(struct_value) => {
+            for (key, field) in &struct_value.fields {
+                path.push(key.clone());
+                collect_shapes(field, path, shapes, all_names);
+                path.pop();
+            }
+
+            if all_names.insert(struct_value.struct_name.clone()) {
+                shapes.entry(shape(value)).or_default().push(Member {
+                    struct_name: struct_value.struct_name.clone(),
+                    path: path.clone(),
+                });
+            }
+        }
+        GenericValue::Array(values) => {
+            // The elements may differ in which optional fields are `None`,
+            // so use their unified type to find the shape.
+            if let Ok(Some(element)) = parsing::unified_type("", values) {
+                collect_shapes(&element, path, shapes, all_names);
+            }
+        }
+        GenericValue::Option(Some(value)) => collect_shapes(value, path, shapes, all_names),
+        _ => (),
+    }
+}
+
+/// A string describing the structure of a value's type, ignoring the names
+/// of any structs.
+pub fn shape(value: &GenericValue) -> String {
+    match value {
+        GenericValue::Struct(struct_value) => {
+            let mut fields = struct_value
+                .fields
+                .iter()
+                .map(|(key, value)| format!("{key}: {}", shape(value)))
+                .collect::<Vec<String>>();
+            // Structs with the same fields in a different order have the same shape.
+            fields.sort();
+            format!("{{{}}}", fields.join(", "))
+        }
+        GenericValue::Array(values) => match parsing::unified_type("", values) {
+            Ok(Some(element)) => format!("[{}]", shape(&element)),
+            _ => "[()]".to_owned(),
+        },
+        GenericValue::Option(Some(value)) => format!("Option<{}>", shape(value)),
+        _ => type_string(value),
+    }
+}
+
+fn shared_name(
+    members: &[Member],
+    all_names: &BTreeSet<String>,
+    options: &StructOptions,
+) -> String {
+    let first = &members[0];
+    let parent = &first.path[..first.path.len() - 1];
+    let siblings = !parent.is_empty()
+        && members
+            .iter()
+            .all(|member| member.path.len() == first.path.len() && member.path.starts_with(parent));
+
+    if siblings {
+        let mut path = parent.to_vec();
+        if let Some(last) = path.last_mut() {
+            *last = singular(last);
+        }
+        let name = options.nested_struct_name(&path);
+        if !all_names.contains(&name) {
+            return name;
+        }
+    }
+
+    first.struct_name.clone()
+}
+
+fn singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{stem}y")
+    } else if word.len() > 1 && word.ends_with('s') && !word.ends_with("ss") {
+        word[..word.len() - 1].to_owned()
+    } else {
+        word.to_owned()
+    }
+}
+
+fn rename_shared(value: &mut GenericValue, shared_names: &BTreeMap<String, String>) {
+    match value {
+        GenericValue::Struct(struct_value) => {
+            if let Some(shared_name) = shared_names.get(&struct_value.struct_name) {
+                struct_value.struct_name.clone_from(shared_name);
+            }
+            for (_, field) in &mut struct_value.fields {
+                rename_shared(field, shared_names);
+            }
+        }
+        GenericValue::Array(values) => {
+            for value in values.iter_mut() {
+                rename_shared(value, shared_names);
+            }
+        }
+        GenericValue::Option(Some(value)) => rename_shared(value, shared_names),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::StructNaming;
+
+    fn table(name: &str, fields: Vec<(&str, GenericValue)>) -> GenericValue {
+        GenericValue::Struct(GenericStruct {
+            struct_name: name.to_owned(),
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+            ..GenericStruct::default()
+        })
+    }
+
+    fn struct_name<'a>(value: &'a GenericValue, key: &str) -> &'a str {
+        match value {
+            GenericValue::Struct(struct_value) => match &struct_value.fields[key] {
+                GenericValue::Struct(field) => &field.struct_name,
+                _ => panic!("Expected a struct field"),
+            },
+            _ => panic!("Expected a struct"),
+        }
+    }
+
+    fn root(fields: Vec<(&str, GenericValue)>) -> GenericStruct {
+        match table("Config", fields) {
+            GenericValue::Struct(root) => root,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn sibling_tables_share_a_struct() {
+        let server = |name: &str| {
+            table(
+                name,
+                vec![
+                    ("host", GenericValue::String("localhost".to_owned())),
+                    ("port", GenericValue::I64(80)),
+                ],
+            )
+        };
+        let mut config = root(vec![(
+            "servers",
+            table(
+                "_Config__servers",
+                vec![
+                    ("alpha", server("_Config__servers__alpha")),
+                    ("beta", server("_Config__servers__beta")),
+                ],
+            ),
+        )]);
+
+        deduplicate_structs(&mut config, &StructOptions::default());
+
+        let servers = &config.fields["servers"];
+        assert_eq!(struct_name(servers, "alpha"), "_Config__server");
+        assert_eq!(struct_name(servers, "beta"), "_Config__server");
+    }
+
+    #[test]
+    fn shared_names_use_the_struct_name() {
+        let server = |name: &str| table(name, vec![("port", GenericValue::I64(80))]);
+        let mut config = root(vec![(
+            "servers",
+            table(
+                "_App__servers",
+                vec![
+                    ("alpha", server("_App__servers__alpha")),
+                    ("beta", server("_App__servers__beta")),
+                ],
+            ),
+        )]);
+        config.struct_name = "App".to_owned();
+
+        let options = StructOptions {
+            struct_name: "App".to_owned(),
+            ..StructOptions::default()
+        };
+        deduplicate_structs(&mut config, &options);
+
+        let servers = &config.fields["servers"];
+        assert_eq!(struct_name(servers, "alpha"), "_App__server");
+        assert_eq!(struct_name(servers, "beta"), "_App__server");
+    }
+
+    #[test]
+    fn shared_names_do_not_collide() {
+        let options = StructOptions {
+            struct_name: "Server".to_owned(),
+            struct_naming: StructNaming::PascalCaseKey,
+            ..StructOptions::default()
+        };
+
+        // `Server` is taken by the root struct.
+        let server = |name: &str| table(name, vec![("port", GenericValue::I64(80))]);
+        let mut config = root(vec![(
+            "servers",
+            table(
+                "Servers",
+                vec![("alpha", server("Alpha")), ("beta", server("Beta"))],
+            ),
+        )]);
+        config.struct_name = "Server".to_owned();
+
+        deduplicate_structs(&mut config, &options);
+
+        let servers = &config.fields["servers"];
+        assert_eq!(struct_name(servers, "alpha"), "Alpha");
+        assert_eq!(struct_name(servers, "beta"), "Alpha");
+
+        // Both groups of siblings would be named `Server`, so only the
+        // first shape to be deduplicated gets it.
+        let host = |name: &str| table(name, vec![("host", GenericValue::Bool(true))]);
+        let mut config = root(vec![
+            (
+                "a",
+                table(
+                    "A",
+                    vec![(
+                        "servers",
+                        table("AServers", vec![("x", server("X")), ("y", server("Y"))]),
+                    )],
+                ),
+            ),
+            (
+                "b",
+                table(
+                    "B",
+                    vec![(
+                        "servers",
+                        table("BServers", vec![("z", host("Z")), ("w", host("W"))]),
+                    )],
+                ),
+            ),
+        ]);
+
+        deduplicate_structs(
+            &mut config,
+            &StructOptions {
+                struct_name: "Config".to_owned(),
+                ..options
+            },
+        );
+
+        let servers = |key: &str| match &config.fields[key] {
+            GenericValue::Struct(value) => &value.fields["servers"],
+            _ => unreachable!(),
+        };
+        assert_eq!(struct_name(servers("a"), "y"), "X");
+        assert_eq!(struct_name(servers("b"), "w"), "Server");
+    }
+
+    #[test]
+    fn distant_tables_use_first_name() {
+        let point = |name: &str| table(name, vec![("x", GenericValue::F64(0.0))]);
+        let mut config = root(vec![
+            (
+                "a",
+                table("_Config__a", vec![("start", point("_Config__a__start"))]),
+            ),
+            (
+                "b",
+                table("_Config__b", vec![("end", point("_Config__b__end"))]),
+            ),
+        ]);
+
+        deduplicate_structs(&mut config, &StructOptions::default());
+
+        assert_eq!(
+            struct_name(&config.fields["a"], "start"),
+            "_Config__a__start"
+        );
+        assert_eq!(struct_name(&config.fields["b"], "end"), "_Config__a__start");
+    }
+
+    #[test]
+    fn different_shapes_are_kept() {
+        let mut config = root(vec![
+            ("a", table("_Config__a", vec![("x", GenericValue::I64(0))])),
+            (
+                "b",
+                table("_Config__b", vec![("x", GenericValue::Bool(false))]),
+            ),
+        ]);
+
+        deduplicate_structs(&mut config, &StructOptions::default());
+
+        let names = config
+            .fields
+            .values()
+            .map(|value| match value {
+                GenericValue::Struct(value) => value.struct_name.clone(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(names, vec!["_Config__a", "_Config__b"]);
+    }
+
+    #[test]
+    fn singular_names() {
+        assert_eq!(singular("servers"), "server");
+        assert_eq!(singular("entries"), "entry");
+        assert_eq!(singular("address"), "address");
+        assert_eq!(singular("s"), "s");
+    }
+}
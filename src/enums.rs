@@ -0,0 +1,399 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+
+use super::{
+    cperror::{Error, GenerationError},
+    files,
+    format::Format,
+    generation,
+    options::{EnumOptions, StructOptions},
+    parsing, validation,
+    value::{GenericStruct, GenericValue},
+};
+
+#[cfg(feature = "json-parsing")]
+use super::json_parsing;
+
+#[cfg(feature = "ron-parsing")]
+use super::ron_parsing;
+
+#[cfg(feature = "toml-parsing")]
+use super::toml_parsing;
+
+#[cfg(feature = "yaml-parsing")]
+use super::yaml_parsing;
+
+/// Generate Rust source code defining an enum based on a map-like config file.
+///
+/// The enum will have one unit variant for each top-level key of the config,
+/// in the order they appear in the file.
+///
+/// The format of the config file will be auto-detected from its extension.
+///
+/// # Examples
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// let code = config_to_struct::generate_enum("items.yaml", &Default::default())?;
+/// assert!(code.contains("pub enum Key"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn generate_enum<P: AsRef<Path>>(filepath: P, options: &EnumOptions) -> Result<String, Error> {
+    let path = filepath.as_ref();
+    let source = std::fs::read_to_string(path)?;
+    let output = generate_enum_from_source_with_filepath(&source, options, Some(path))?;
+
+    Ok(output)
+}
+
+/// Generate Rust source code defining an enum from a config string
+/// in a format specified in the provided options.
+///
+/// # Examples
+/// ```rust
+/// # #[cfg(feature = "toml-parsing")]
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::{EnumOptions, Format};
+///
+/// let code = config_to_struct::generate_enum_from_source(
+///     "[ItemOne]\n[ItemTwo]\n",
+///     &EnumOptions {
+///         format: Some(Format::Toml),
+///         ..Default::default()
+///     })?;
+///
+/// assert!(code.contains("pub enum Key"));
+/// assert!(code.contains("    ItemOne,\n    ItemTwo,"));
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "toml-parsing"))]
+/// # fn main() {}
+/// ```
+pub fn generate_enum_from_source<S: AsRef<str>>(
+    source: S,
+    options: &EnumOptions,
+) -> Result<String, GenerationError> {
+    generate_enum_from_source_with_filepath(source.as_ref(), options, None)
+}
+
+fn generate_enum_from_source_with_filepath(
+    source: &str,
+    options: &EnumOptions,
+    filepath: Option<&Path>,
+) -> Result<String, GenerationError> {
+    let tokens = generate_enum_tokens_with_filepath(source, options, filepath)?;
+
+    Ok(generation::render_file(tokens))
+}
+
+fn generate_enum_tokens_with_filepath(
+    source: &str,
+    options: &EnumOptions,
+    filepath: Option<&Path>,
+) -> Result<TokenStream, GenerationError> {
+    options.validate()?;
+
+    let format = match options.format {
+        Some(format) => format,
+        None => match filepath {
+            Some(path) => Format::from_filename(path)?,
+            None => return Err(GenerationError::UnknownInputFormat("<none>".into())),
+        },
+    };
+
+    let keys = match format {
+        #[cfg(feature = "json-parsing")]
+        Format::Json => json_parsing::parse_map_keys(source),
+
+        #[cfg(feature = "ron-parsing")]
+        Format::Ron => ron_parsing::parse_map_keys(source),
+
+        #[cfg(feature = "toml-parsing")]
+        Format::Toml => toml_parsing::parse_map_keys(source),
+
+        #[cfg(feature = "yaml-parsing")]
+        Format::Yaml => yaml_parsing::parse_map_keys(source),
+    }
+    .map_err(|error| error.in_file(filepath))?;
+
+    validation::validate_keys(&keys, options.edition)?;
+
+    let data = match options.data_struct_options() {
+        Some(struct_options) => {
+            let data = parse_variant_data(source, format, &keys, &struct_options)
+                .map_err(|error| error.in_file(filepath))?;
+            Some((data, struct_options))
+        }
+        None => None,
+    };
+
+    let mut tokens = generation::generate_enum(&keys, options);
+
+    if let Some((data, struct_options)) = data {
+        tokens.extend(generation::generate_enum_data(
+            &data,
+            options,
+            &struct_options,
+        ));
+    }
+
+    Ok(tokens)
+}
+
+fn parse_variant_data(
+    source: &str,
+    format: Format,
+    keys: &[String],
+    options: &StructOptions,
+) -> Result<Vec<GenericValue>, GenerationError> {
+    let mut root_struct: GenericStruct = match format {
+        #[cfg(feature = "json-parsing")]
+        Format::Json => json_parsing::parse_json(source, options)?,
+
+        #[cfg(feature = "ron-parsing")]
+        Format::Ron => ron_parsing::parse_ron(source, options)?,
+
+        #[cfg(feature = "toml-parsing")]
+        Format::Toml => toml_parsing::parse_toml(source, options)?,
+
+        #[cfg(feature = "yaml-parsing")]
+        Format::Yaml => yaml_parsing::parse_yaml(source, options)?,
+    };
+
+    parsing::sort_fields(&mut root_struct);
+
+    let data_struct_name = &options.struct_name;
+    let nested_prefix = format!("_{data_struct_name}");
+
+    let mut data = keys
+        .iter()
+        .map(|key| {
+            let mut value = root_struct
+                .fields
+                .remove(key)
+                .ok_or_else(|| GenerationError::InvalidVariantName(key.clone()))?;
+            parsing::rename_structs(&mut value, data_struct_name, &nested_prefix);
+            Ok(value)
+        })
+        .collect::<Result<Vec<GenericValue>, GenerationError>>()?;
+
+    validation::validate_variant_data(&data, options)?;
+    parsing::unify_values("", &mut data)?;
+
+    Ok(data)
+}
+
+/// Generate a Rust module containing an enum definition based on a
+/// given config file containing a map-like structure.
+///
+/// The format of the config is auto-detected from its filename
+/// extension.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::EnumOptions;
+///
+/// config_to_struct::create_enum("items.yaml", "src/items.rs", &EnumOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_enum<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
+    filepath: SrcPath,
+    destination: DstPath,
+    options: &EnumOptions,
+) -> Result<(), Error> {
+    create_enum_with_directives(filepath, destination, options, &mut std::io::stdout())
+}
+
+/// The same as [`create_enum`](fn.create_enum.html), except that any
+/// `cargo:rerun-if-changed` directives are written to `directives`
+/// instead of being printed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::EnumOptions;
+///
+/// let mut directives = Vec::new();
+/// config_to_struct::create_enum_with_directives(
+///     "items.yaml",
+///     "src/items.rs",
+///     &EnumOptions {
+///         emit_rerun_if_changed: true,
+///         ..Default::default()
+///     },
+///     &mut directives)?;
+///
+/// assert_eq!(directives, b"cargo:rerun-if-changed=items.yaml\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_enum_with_directives<SrcPath, DstPath, W>(
+    filepath: SrcPath,
+    destination: DstPath,
+    options: &EnumOptions,
+    directives: &mut W,
+) -> Result<(), Error>
+where
+    SrcPath: AsRef<Path>,
+    DstPath: AsRef<Path>,
+    W: Write,
+{
+    write_enum(
+        filepath.as_ref(),
+        destination.as_ref(),
+        options,
+        directives,
+        generation::render_file,
+    )
+}
+
+/// Writes the enum generated from the config, rendered by `render`, to
+/// `destination`, after writing any `cargo:rerun-if-changed` directives to
+/// `directives`.
+fn write_enum<W: Write>(
+    filepath: &Path,
+    destination: &Path,
+    options: &EnumOptions,
+    directives: &mut W,
+    render: fn(TokenStream) -> String,
+) -> Result<(), Error> {
+    if options.emit_rerun_if_changed {
+        files::write_rerun_directives(directives, &[filepath])?;
+    }
+
+    let source = std::fs::read_to_string(filepath)?;
+    let tokens = generate_enum_tokens_with_filepath(&source, options, Some(filepath))?;
+    files::ensure_destination(destination, options.create_dirs)?;
+    files::write_destination(destination, render(tokens), options.write_only_if_changed)?;
+
+    Ok(())
+}
+
+/// Generate a Rust file containing an enum definition based on a
+/// given config file, and write it to the build script's `OUT_DIR`.
+///
+/// The generated file has no inner attributes, so that it can be
+/// included with `include!`. The path that was written to is
+/// returned.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// // build.rs
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::EnumOptions;
+///
+/// config_to_struct::create_enum_in_out_dir("items.yaml", "items.rs", &EnumOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ```rust,ignore
+/// // main.rs
+/// include!(concat!(env!("OUT_DIR"), "/items.rs"));
+/// ```
+pub fn create_enum_in_out_dir<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
+    filepath: SrcPath,
+    filename: DstPath,
+    options: &EnumOptions,
+) -> Result<PathBuf, Error> {
+    create_enum_in_out_dir_with_directives(filepath, filename, options, &mut std::io::stdout())
+}
+
+/// The same as [`create_enum_in_out_dir`](fn.create_enum_in_out_dir.html),
+/// except that any `cargo:rerun-if-changed` directives are written to
+/// `directives` instead of being printed.
+pub fn create_enum_in_out_dir_with_directives<SrcPath, DstPath, W>(
+    filepath: SrcPath,
+    filename: DstPath,
+    options: &EnumOptions,
+    directives: &mut W,
+) -> Result<PathBuf, Error>
+where
+    SrcPath: AsRef<Path>,
+    DstPath: AsRef<Path>,
+    W: Write,
+{
+    let destination = files::out_dir_destination(files::out_dir(), filename.as_ref())?;
+    write_enum(
+        filepath.as_ref(),
+        &destination,
+        options,
+        directives,
+        generation::render_items,
+    )?;
+
+    Ok(destination)
+}
+
+/// Generate a Rust module containing an enum definition from a
+/// config string in a format specified by the provided options.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # #[cfg(feature = "toml-parsing")]
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::{EnumOptions, Format};
+///
+/// config_to_struct::create_enum_from_source(
+///     "[ItemOne]\n[ItemTwo]\n",
+///     "src/items.rs",
+///     &EnumOptions {
+///         format: Some(Format::Toml),
+///         ..Default::default()
+///     })?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "toml-parsing"))]
+/// # fn main() {}
+/// ```
+pub fn create_enum_from_source<S: AsRef<str>, P: AsRef<Path>>(
+    source: S,
+    destination: P,
+    options: &EnumOptions,
+) -> Result<(), Error> {
+    let output = generate_enum_from_source(source, options)?;
+    files::ensure_destination(destination.as_ref(), options.create_dirs)?;
+    files::write_destination(destination.as_ref(), output, options.write_only_if_changed)?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "toml-parsing"))]
+mod tests {
+    use super::*;
+    use crate::cperror::OptionsError;
+
+    #[test]
+    fn data_struct_names() {
+        let source = "[ItemOne]\nweight = 1\n[ItemTwo]\nweight = 2\n";
+        let options = |data_struct_name: &str| EnumOptions {
+            format: Some(Format::Toml),
+            enum_name: "Item".to_owned(),
+            data_struct_name: Some(data_struct_name.to_owned()),
+            ..EnumOptions::default()
+        };
+
+        let code = generate_enum_from_source(source, &options("ItemData")).unwrap();
+        assert!(code.contains("pub struct ItemData"));
+
+        assert!(matches!(
+            generate_enum_from_source(source, &options("Item")),
+            Err(GenerationError::StructOptions(OptionsError::DataStructNameConflict(name)))
+                if name == "Item"
+        ));
+        assert!(matches!(
+            generate_enum_from_source(source, &options("item data")),
+            Err(GenerationError::StructOptions(
+                OptionsError::InvalidStructName(_)
+            ))
+        ));
+    }
+}
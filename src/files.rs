@@ -1,4 +1,8 @@
-use std::path::Path;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::cperror::Error;
 
 pub fn ensure_destination(path: &Path, create_dirs: bool) -> Result<(), std::io::Error> {
     if create_dirs {
@@ -10,6 +14,15 @@ pub fn ensure_destination(path: &Path, create_dirs: bool) -> Result<(), std::io:
     Ok(())
 }
 
+pub fn out_dir() -> Option<OsString> {
+    std::env::var_os("OUT_DIR")
+}
+
+pub fn out_dir_destination(out_dir: Option<OsString>, filename: &Path) -> Result<PathBuf, Error> {
+    let out_dir = out_dir.ok_or(Error::MissingOutDir)?;
+    Ok(PathBuf::from(out_dir).join(filename))
+}
+
 pub fn write_destination(
     destination: &Path,
     output: String,
@@ -32,3 +45,13 @@ pub fn write_destination(
     }
 }
 
+pub fn write_rerun_directives<W: Write>(
+    output: &mut W,
+    paths: &[&Path],
+) -> Result<(), std::io::Error> {
+    for path in paths {
+        writeln!(output, "cargo:rerun-if-changed={}", path.display())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,374 @@
+//! Generating enums from the files in a directory. (Requires the
+//! `experimental-files-enum` feature.)
+//!
+//! This module is experimental and its API is subject to change.
+//!
+//! Given a directory such as `assets/` containing `player.png` and
+//! `theme.ogg`, this generates an enum with one variant per file:
+//!
+//! ```rust,no_run
+//! // build.rs
+//! use config_to_struct::{files_enum::{self, FilesEnumOptions}, Error};
+//!
+//! fn main() -> Result<(), Error> {
+//!     files_enum::create_files_enum(
+//!         "assets",
+//!         "src/assets.rs",
+//!         &FilesEnumOptions {
+//!             enum_name: "Asset".to_owned(),
+//!             include_bytes: true,
+//!             ..Default::default()
+//!         })
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! // assets.rs
+//! // ...
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+//! pub enum Asset {
+//!     PlayerPng,
+//!     ThemeOgg,
+//! }
+//! impl Asset {
+//!     pub const ALL: &'static [Asset] = &[Asset::PlayerPng, Asset::ThemeOgg];
+//! }
+//! // ...
+//! impl Asset {
+//!     pub const fn path(self) -> &'static str {
+//!         match self {
+//!             Asset::PlayerPng => "assets/player.png",
+//!             Asset::ThemeOgg => "assets/theme.ogg",
+//!         }
+//!     }
+//!     pub const fn bytes(self) -> &'static [u8] {
+//!         match self {
+//!             Asset::PlayerPng => include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/player.png")),
+//!             Asset::ThemeOgg => include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/theme.ogg")),
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Because `bytes()` uses `include_bytes!`, removing or renaming a file
+//! is caught at compile time rather than when the asset is loaded.
+
+use std::path::{Path, PathBuf};
+
+use case::CaseExt;
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+use crate::{
+    cperror::{Error, GenerationError},
+    files, generation,
+    options::{Edition, EnumOptions, SerdeSupport},
+    validation,
+};
+
+/// Options for configuring the generation of a files enum.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilesEnumOptions {
+    /// The name of the resulting enum.
+    ///
+    /// Defaults to `"FileKey"`.
+    pub enum_name: String,
+
+    /// The name of the const slice containing all variants.
+    ///
+    /// If you specify `None` then no constant will be
+    /// generated.
+    ///
+    /// Defaults to `Some("ALL")`.
+    pub all_variants_const: Option<String>,
+
+    /// A list of traits for the enum to derive.
+    ///
+    /// Defaults to `["Debug", "Clone", "Copy", "PartialEq",
+    /// "Eq", "PartialOrd", "Ord", "Hash"]`
+    pub derived_traits: Vec<String>,
+
+    /// Whether to implement the `Display` trait for this enum.
+    /// This requires the `Debug` trait to be implemented.
+    ///
+    /// Defaults to `false`.
+    pub impl_display: bool,
+
+    /// Whether to generate a `bytes()` function which returns
+    /// the contents of each file via `include_bytes!`.
+    ///
+    /// Defaults to `false`.
+    pub include_bytes: bool,
+
+    /// Whether or not to create the parent directories of the
+    /// output file, if they don't exist.
+    ///
+    /// Defaults to `true`.
+    pub create_dirs: bool,
+
+    /// Whether to check if the destination file would be changed
+    /// before writing output.
+    ///
+    /// Defaults to `true`.
+    pub write_only_if_changed: bool,
+
+    /// Whether `create_files_enum` should print a
+    /// `cargo:rerun-if-changed` directive for the directory, so
+    /// that the build script reruns whenever a file is added or
+    /// removed.
+    ///
+    /// Defaults to `false`.
+    pub emit_rerun_if_changed: bool,
+}
+
+impl FilesEnumOptions {
+    fn enum_options(&self) -> EnumOptions {
+        EnumOptions {
+            format: None,
+            enum_name: self.enum_name.clone(),
+            all_variants_const: self.all_variants_const.clone(),
+            derived_traits: self.derived_traits.clone(),
+            first_variant_is_default: false,
+            impl_display: self.impl_display,
+            impl_from_str: false,
+            serde_support: SerdeSupport::No,
+            use_serde_derive_crate: false,
+            create_dirs: self.create_dirs,
+            write_only_if_changed: self.write_only_if_changed,
+            emit_rerun_if_changed: self.emit_rerun_if_changed,
+            data_struct_name: None,
+            edition: Edition::default(),
+        }
+    }
+}
+
+impl Default for FilesEnumOptions {
+    /// ```rust
+    /// use config_to_struct::files_enum::FilesEnumOptions;
+    ///
+    /// let default_options = FilesEnumOptions {
+    ///     enum_name: "FileKey".to_owned(),
+    ///     all_variants_const: Some("ALL".to_owned()),
+    ///     derived_traits: vec![
+    ///         "Debug".to_owned(),
+    ///         "Clone".to_owned(),
+    ///         "Copy".to_owned(),
+    ///         "PartialEq".to_owned(),
+    ///         "Eq".to_owned(),
+    ///         "PartialOrd".to_owned(),
+    ///         "Ord".to_owned(),
+    ///         "Hash".to_owned(),
+    ///     ],
+    ///     impl_display: false,
+    ///     include_bytes: false,
+    ///     create_dirs: true,
+    ///     write_only_if_changed: true,
+    ///     emit_rerun_if_changed: false,
+    /// };
+    /// assert_eq!(default_options, FilesEnumOptions::default());
+    /// ```
+    fn default() -> Self {
+        FilesEnumOptions {
+            enum_name: "FileKey".to_owned(),
+            all_variants_const: Some("ALL".to_owned()),
+            derived_traits: EnumOptions::default().derived_traits,
+            impl_display: false,
+            include_bytes: false,
+            create_dirs: true,
+            write_only_if_changed: true,
+            emit_rerun_if_changed: false,
+        }
+    }
+}
+
+/// Generate Rust source code defining an enum with one variant
+/// for each file in the given directory.
+///
+/// Subdirectories and hidden files (those starting with `.`) are
+/// skipped. Variants are named after the full filename in
+/// `PascalCase`, so `player_sprite.png` becomes `PlayerSpritePng`.
+///
+/// # Examples
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::files_enum;
+///
+/// let code = files_enum::generate_files_enum("assets", &Default::default())?;
+/// assert!(code.contains("pub enum FileKey"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn generate_files_enum<P: AsRef<Path>>(
+    directory: P,
+    options: &FilesEnumOptions,
+) -> Result<String, Error> {
+    let enum_options = options.enum_options();
+    enum_options.validate().map_err(GenerationError::from)?;
+
+    let directory = directory.as_ref();
+    let filenames = list_files(directory)?;
+
+    let mut variants = Vec::with_capacity(filenames.len());
+    for filename in &filenames {
+        let variant = variant_name(filename);
+        if variants.contains(&variant) {
+            return Err(GenerationError::DuplicateVariantName(variant).into());
+        }
+        variants.push(variant);
+    }
+    validation::validate_keys(&variants, enum_options.edition)?;
+
+    let paths = filenames
+        .iter()
+        .map(|filename| directory.join(filename))
+        .collect::<Vec<PathBuf>>();
+
+    let mut tokens = generation::generate_enum(&variants, &enum_options);
+    tokens.extend(files_impl(&variants, &paths, options));
+
+    Ok(generation::render_file(tokens))
+}
+
+/// Generate a Rust module containing an enum with one variant
+/// for each file in the given directory.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::files_enum;
+///
+/// files_enum::create_files_enum("assets", "src/assets.rs", &Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_files_enum<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
+    directory: SrcPath,
+    destination: DstPath,
+    options: &FilesEnumOptions,
+) -> Result<(), Error> {
+    if options.emit_rerun_if_changed {
+        files::write_rerun_directives(&mut std::io::stdout(), &[directory.as_ref()])?;
+    }
+
+    let output = generate_files_enum(directory, options)?;
+    files::ensure_destination(destination.as_ref(), options.create_dirs)?;
+    files::write_destination(destination.as_ref(), output, options.write_only_if_changed)?;
+
+    Ok(())
+}
+
+fn list_files(directory: &Path) -> Result<Vec<String>, Error> {
+    let mut filenames = vec![];
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let filename = entry.file_name();
+        let filename = filename.to_str().ok_or_else(|| {
+            GenerationError::InvalidVariantName(filename.to_string_lossy().into())
+        })?;
+
+        if !filename.starts_with('.') {
+            filenames.push(filename.to_owned());
+        }
+    }
+    filenames.sort();
+
+    Ok(filenames)
+}
+
+fn variant_name(filename: &str) -> String {
+    let snake = filename
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+
+    snake.to_lowercase().to_camel()
+}
+
+fn files_impl(variants: &[String], paths: &[PathBuf], options: &FilesEnumOptions) -> TokenStream {
+    let enum_name = generation::ident(&options.enum_name);
+    let variants = variants
+        .iter()
+        .map(|variant| generation::ident(variant))
+        .collect::<Vec<Ident>>();
+
+    let display_paths = paths
+        .iter()
+        .map(|path| path.display().to_string().replace('\\', "/"))
+        .collect::<Vec<String>>();
+
+    let mut tokens = quote! {
+        pub const fn path(self) -> &'static str {
+            match self {
+                #(#enum_name::#variants => #display_paths,)*
+            }
+        }
+    };
+
+    if options.include_bytes {
+        let include_paths = paths
+            .iter()
+            .zip(&display_paths)
+            .map(|(path, display_path)| {
+                if path.is_absolute() {
+                    quote!(#display_path)
+                } else {
+                    let display_path = format!("/{display_path}");
+                    quote!(concat!(env!("CARGO_MANIFEST_DIR"), #display_path))
+                }
+            });
+
+        tokens.extend(quote! {
+            pub const fn bytes(self) -> &'static [u8] {
+                match self {
+                    #(#enum_name::#variants => include_bytes!(#include_paths),)*
+                }
+            }
+        });
+    }
+
+    quote! {
+        impl #enum_name {
+            #tokens
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_names() {
+        assert_eq!(variant_name("player.png"), "PlayerPng");
+        assert_eq!(variant_name("player_sprite.png"), "PlayerSpritePng");
+        assert_eq!(variant_name("theme-song.OGG"), "ThemeSongOgg");
+        assert_eq!(variant_name("LICENSE"), "License");
+    }
+
+    #[test]
+    fn path_and_bytes_accessors() {
+        let variants = vec!["PlayerPng".to_owned()];
+        let paths = vec![Path::new("assets").join("player.png")];
+        let code = files_impl(
+            &variants,
+            &paths,
+            &FilesEnumOptions {
+                include_bytes: true,
+                ..Default::default()
+            },
+        );
+
+        let code = code.to_string();
+        assert!(code.contains(&quote!(FileKey::PlayerPng => "assets/player.png",).to_string()));
+        assert!(code.contains(
+            &quote!(FileKey::PlayerPng => include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/player.png")),)
+                .to_string()
+        ));
+    }
+}
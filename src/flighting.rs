@@ -0,0 +1,298 @@
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use super::cperror::GenerationError;
+use super::json_parsing;
+use super::options::StructOptions;
+use super::parsing;
+use super::synchronization::SYNCHRONIZATION_FILE_EXTENSION;
+use super::value::{GenericStruct, GenericValue};
+
+/// The settings of a flighting file, which patch a base config.
+///
+/// A flighting file is a JSON object with two optional members:
+///
+/// ```json
+/// {
+///     "settings": { "database.pool_size": 8 },
+///     "flights": [
+///         { "name": "BigCache", "enabled": true, "settings": { "cache": { "size": 1024 } } }
+///     ]
+/// }
+/// ```
+///
+/// Settings are keyed by a dotted path into the base config. The top-level
+/// settings are applied first, followed by the settings of each enabled
+/// flight in order. Flights are enabled unless `enabled` is `false`.
+#[derive(Debug)]
+pub struct Flighting {
+    settings: Vec<(String, GenericValue)>,
+}
+
+/// Whether the file is a flighting file rather than a config layer.
+pub fn is_flighting_file(filepath: &Path) -> bool {
+    filepath
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(&format!(".{SYNCHRONIZATION_FILE_EXTENSION}")))
+}
+
+/// Parses a flighting file.
+pub fn parse_flighting(
+    source: &str,
+    options: &StructOptions,
+) -> Result<Flighting, GenerationError> {
+    let root: Map<String, Value> =
+        serde_json::from_str(source).map_err(|err| json_parsing::json_error(err, source))?;
+
+    let mut settings = Vec::new();
+    if let Some(value) = root.get("settings") {
+        collect_settings(value, "settings", options, &mut settings)?;
+    }
+
+    let flights = match root.get("flights") {
+        None => &[][..],
+        Some(Value::Array(flights)) => &flights[..],
+        Some(_) => return Err(invalid("`flights` must be an array")),
+    };
+    for (index, flight) in flights.iter().enumerate() {
+        let flight = flight
+            .as_object()
+            .ok_or_else(|| invalid(&format!("flight {index} must be an object")))?;
+        let name = match flight.get("name") {
+            Some(Value::String(name)) => name.clone(),
+            _ => {
+                return Err(invalid(&format!(
+                    "flight {index} must have a string `name`"
+                )))
+            }
+        };
+        let enabled = match flight.get("enabled") {
+            None => true,
+            Some(Value::Bool(enabled)) => *enabled,
+            Some(_) => {
+                return Err(invalid(&format!(
+                    "`enabled` of flight `{name}` must be a bool"
+                )))
+            }
+        };
+
+        if let (true, Some(value)) = (enabled, flight.get("settings")) {
+            collect_settings(
+                value,
+                &format!("settings of flight `{name}`"),
+                options,
+                &mut settings,
+            )?;
+        }
+    }
+
+    Ok(Flighting { settings })
+}
+
+fn collect_settings(
+    value: &Value,
+    description: &str,
+    options: &StructOptions,
+    settings: &mut Vec<(String, GenericValue)>,
+) -> Result<(), GenerationError> {
+    let map = value
+        .as_object()
+        .ok_or_else(|| invalid(&format!("the {description} must be an object")))?;
+
+    // Name any tables the same way as the tables of the config they patch.
+    let super_struct = format!("_{}", options.struct_name);
+    for (path, value) in map {
+        let super_key = path.replace('.', "__");
+        let value = json_parsing::json_to_raw_value(
+            &super_struct,
+            &super_key,
+            path,
+            value.clone(),
+            options,
+        )?;
+        settings.push((path.clone(), value));
+    }
+    Ok(())
+}
+
+fn invalid(message: &str) -> GenerationError {
+    GenerationError::InvalidFlighting(message.to_owned())
+}
+
+impl Flighting {
+    /// Patches the settings onto the base config.
+    ///
+    /// Every setting must already exist in the base config and keep its
+    /// type, so that the patched config has the same generated struct.
+    pub fn apply(self, base: &mut GenericStruct) -> Result<(), GenerationError> {
+        for (path, value) in self.settings {
+            let keys: Vec<&str> = path.split('.').collect();
+            patch_struct(base, &keys, &path, value)?;
+        }
+        Ok(())
+    }
+}
+
+fn patch_struct(
+    struct_value: &mut GenericStruct,
+    keys: &[&str],
+    path: &str,
+    value: GenericValue,
+) -> Result<(), GenerationError> {
+    let (key, rest) = keys
+        .split_first()
+        .ok_or_else(|| GenerationError::UnknownFlightingSetting(path.to_owned()))?;
+    let field = struct_value
+        .fields
+        .get_mut(*key)
+        .ok_or_else(|| GenerationError::UnknownFlightingSetting(path.to_owned()))?;
+
+    match (field, rest.is_empty()) {
+        (field, true) => patch_value(field, path, value),
+        (GenericValue::Struct(nested), false) => patch_struct(nested, rest, path, value),
+        (GenericValue::Option(Some(nested)), false) => match &mut **nested {
+            GenericValue::Struct(nested) => patch_struct(nested, rest, path, value),
+            _ => Err(GenerationError::UnknownFlightingSetting(path.to_owned())),
+        },
+        _ => Err(GenerationError::UnknownFlightingSetting(path.to_owned())),
+    }
+}
+
+fn patch_value(
+    field: &mut GenericValue,
+    path: &str,
+    value: GenericValue,
+) -> Result<(), GenerationError> {
+    match (field, value) {
+        // Structs are patched key by key, so that the settings only need to
+        // mention the keys which change.
+        (GenericValue::Struct(field), GenericValue::Struct(value)) => {
+            for (key, value) in value.fields {
+                patch_struct(field, &[&key], &format!("{path}.{key}"), value)?;
+            }
+            Ok(())
+        }
+        (field, value) => {
+            *field = parsing::coerce_like(value, field)
+                .ok_or_else(|| GenerationError::MismatchedFlightingSetting(path.to_owned()))?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::type_string;
+
+    fn base() -> GenericStruct {
+        json_parsing::parse_json(
+            r#"{ "name": "app", "ratio": 0.5, "cache": { "size": 16, "ttl": 60 }, "hosts": ["a"] }"#,
+            &StructOptions::default(),
+        )
+        .unwrap()
+    }
+
+    fn patch(flighting: &str) -> Result<GenericStruct, GenerationError> {
+        let mut config = base();
+        parse_flighting(flighting, &StructOptions::default())?.apply(&mut config)?;
+        Ok(config)
+    }
+
+    fn field<'a>(config: &'a GenericStruct, path: &str) -> &'a GenericValue {
+        let mut keys = path.split('.');
+        let mut value = &config.fields[keys.next().unwrap()];
+        for key in keys {
+            value = match value {
+                GenericValue::Struct(nested) => &nested.fields[key],
+                _ => panic!("expected a struct"),
+            };
+        }
+        value
+    }
+
+    #[test]
+    fn filenames() {
+        assert!(is_flighting_file(Path::new("sync/app.settingsconfig.json")));
+        assert!(!is_flighting_file(Path::new("settingsconfig.json")));
+        assert!(!is_flighting_file(Path::new("app.json")));
+    }
+
+    #[test]
+    fn settings_and_flights() {
+        let config = patch(
+            r#"{
+                "settings": { "name": "patched", "cache.size": 32, "ratio": 1 },
+                "flights": [
+                    { "name": "Off", "enabled": false, "settings": { "name": "off" } },
+                    { "name": "On", "settings": { "cache": { "ttl": 5 }, "hosts": ["b", "c"] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(matches!(field(&config, "name"), GenericValue::String(name) if name == "patched"));
+        assert!(matches!(
+            field(&config, "cache.size"),
+            GenericValue::I64(32)
+        ));
+        assert!(matches!(field(&config, "cache.ttl"), GenericValue::I64(5)));
+        assert!(matches!(field(&config, "hosts"), GenericValue::Array(hosts) if hosts.len() == 2));
+
+        // Integers are widened to the type of the base setting.
+        assert_eq!(type_string(field(&config, "ratio")), "f64");
+    }
+
+    #[test]
+    fn nested_struct_names() {
+        let options = StructOptions {
+            struct_name: "App".to_owned(),
+            ..StructOptions::default()
+        };
+        let flighting = parse_flighting(
+            r#"{ "settings": { "cache": { "size": 32 }, "db.pool": { "size": 4 } } }"#,
+            &options,
+        )
+        .unwrap();
+
+        let names = flighting
+            .settings
+            .iter()
+            .map(|(_, value)| match value {
+                GenericValue::Struct(value) => value.struct_name.as_str(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["_App__cache", "_App__db__pool"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            patch(r#"{ "settings": { "cache.missing": 1 } }"#),
+            Err(GenerationError::UnknownFlightingSetting(path)) if path == "cache.missing"
+        ));
+        assert!(matches!(
+            patch(r#"{ "settings": { "name.nested": 1 } }"#),
+            Err(GenerationError::UnknownFlightingSetting(_))
+        ));
+        assert!(matches!(
+            patch(r#"{ "settings": { "cache": { "size": "big" } } }"#),
+            Err(GenerationError::MismatchedFlightingSetting(path)) if path == "cache.size"
+        ));
+        assert!(matches!(
+            patch(r#"{ "settings": { "hosts": [1] } }"#),
+            Err(GenerationError::MismatchedFlightingSetting(_))
+        ));
+        assert!(matches!(
+            patch(r#"{ "flights": [{ "enabled": true }] }"#),
+            Err(GenerationError::InvalidFlighting(_))
+        ));
+        assert!(matches!(
+            patch(r#"{ "flights": {} }"#),
+            Err(GenerationError::InvalidFlighting(_))
+        ));
+    }
+}
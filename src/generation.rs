@@ -1,88 +1,326 @@
-use super::options::StructOptions;
+use super::naming;
+use super::options::{EnumOptions, SerdeSupport, StructOptions};
+use super::parsing;
+use super::validation;
 use super::value::{GenericStruct, GenericValue};
-use convert_case::{Case, Casing};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::{quote, ToTokens};
+use std::collections::BTreeSet;
 
-pub fn generate_structs(struct_value: &GenericStruct, options: &StructOptions) -> String {
-    let mut buffer = String::new();
-    generate_struct_declarations(&mut buffer, struct_value, options);
-    buffer
+/// Render generated items as the contents of a Rust source file.
+pub fn render_file(items: TokenStream) -> String {
+    render(quote! {
+        #![cfg_attr(rustfmt, rustfmt_skip)]
+        #![allow(dead_code)]
+
+        #items
+    })
+}
+
+/// Render generated items without any inner attributes, which are
+/// not allowed in a file used with `include!`. Each item gets its own
+/// `#[allow(dead_code)]` instead.
+pub fn render_items(items: TokenStream) -> String {
+    match syn::parse2::<syn::File>(items.clone()) {
+        Ok(file) => {
+            let items = file.items;
+            render(quote! { #( #[allow(dead_code)] #items )* })
+        }
+        Err(_) => items.to_string(),
+    }
+}
+
+fn render(file: TokenStream) -> String {
+    match syn::parse2::<syn::File>(file.clone()) {
+        Ok(file) => prettyplease::unparse(&file),
+        // Still write out what we have so the compiler can point at the problem.
+        Err(_) => file.to_string(),
+    }
+}
+
+pub fn ident(name: &str) -> Ident {
+    Ident::new(name, Span::call_site())
+}
+
+/// The identifier of the struct field generated for a config key.
+pub fn field_ident(key: &str, options: &StructOptions) -> Ident {
+    let name = naming::field_name(key, options);
+    // Validation only lets through keywords which may be raw identifiers.
+    if validation::is_keyword(&name, options.edition) {
+        Ident::new_raw(&name, Span::call_site())
+    } else {
+        ident(&name)
+    }
+}
+
+pub fn generate_structs(struct_value: &GenericStruct, options: &StructOptions) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    generate_struct_declarations(&mut tokens, &mut BTreeSet::new(), struct_value, options);
+    tokens
 }
 
-#[allow(clippy::format_push_string, clippy::get_first)]
 fn generate_struct_declarations(
-    output: &mut String,
+    output: &mut TokenStream,
+    declared: &mut BTreeSet<String>,
     struct_value: &GenericStruct,
     options: &StructOptions,
 ) {
-    let field_strings = struct_value
+    if !declared.insert(struct_value.struct_name.clone()) {
+        return;
+    }
+
+    let struct_name = ident(&struct_value.struct_name);
+    let struct_doc = doc_tokens(struct_value.doc.as_deref());
+    let field_docs = struct_value
         .fields
-        .iter()
-        .map(|(name, value)| {
-            format!(
-                "    pub {}: {},",
-                name.to_case(Case::Snake),
-                type_string_with_options(value, options.max_array_size)
-            )
-        })
-        .collect::<Vec<String>>();
-
-    let derive_string = {
-        let mut derived_traits = options.derived_traits.clone();
-        if let Some((ser, de)) = options.serde_support.should_derive_ser_de() {
-            let prefix = if options.use_serde_derive_crate {
-                "serde_derive::"
-            } else {
-                "serde::"
-            };
+        .keys()
+        .map(|key| doc_tokens(struct_value.field_docs.get(key).map(String::as_str)));
+    let field_renames = struct_value
+        .fields
+        .keys()
+        .map(|key| rename_tokens(key, options));
+    let field_names = struct_value
+        .fields
+        .keys()
+        .map(|key| field_ident(key, options));
+    let field_types = struct_value
+        .fields
+        .values()
+        .map(|value| type_tokens(value, options.max_array_size));
 
-            if ser {
-                derived_traits.push(format!("{prefix}Serialize"));
-            }
-            if de {
-                derived_traits.push(format!("{prefix}Deserialize"));
+    let derive = derive_tokens(
+        &options.derived_traits,
+        options.serde_support,
+        options.use_serde_derive_crate,
+    );
+
+    output.extend(quote! {
+        #struct_doc
+        #derive
+        #[allow(non_camel_case_types, non_snake_case, clippy::struct_excessive_bools, clippy::struct_field_names)]
+        pub struct #struct_name {
+            #(#field_docs #field_renames pub #field_names: #field_types,)*
+        }
+    });
+
+    for value in struct_value.fields.values() {
+        generate_nested_struct_declarations(output, declared, value, options);
+    }
+}
+
+fn generate_nested_struct_declarations(
+    output: &mut TokenStream,
+    declared: &mut BTreeSet<String>,
+    value: &GenericValue,
+    options: &StructOptions,
+) {
+    match *value {
+        GenericValue::Struct(ref value) => {
+            generate_struct_declarations(output, declared, value, options);
+        }
+        GenericValue::Array(ref values) => {
+            if let Ok(Some(value)) = parsing::unified_type("", values) {
+                generate_nested_struct_declarations(output, declared, &value, options);
             }
         }
+        GenericValue::Option(Some(ref value)) => {
+            generate_nested_struct_declarations(output, declared, value, options);
+        }
+        _ => (),
+    }
+}
+
+/// A serde attribute naming the config key of a field, if the field name
+/// differs from it, so that the struct can still be loaded from the config.
+fn rename_tokens(key: &str, options: &StructOptions) -> TokenStream {
+    let renamed = options.serde_support.should_derive_ser_de().is_some()
+        && naming::field_name(key, options) != key;
+
+    if renamed {
+        quote! { #[serde(rename = #key)] }
+    } else {
+        TokenStream::new()
+    }
+}
+
+/// Doc attributes for each line of a comment.
+fn doc_tokens(doc: Option<&str>) -> TokenStream {
+    let lines = doc.into_iter().flat_map(str::lines);
+    quote! { #(#[doc = #lines])* }
+}
+
+/// An inner doc attribute for the generated file, from the comment at the
+/// top of the config.
+pub fn generate_file_doc(struct_value: &GenericStruct) -> TokenStream {
+    let lines = struct_value.doc.iter().flat_map(|doc| doc.lines());
+    quote! { #(#![doc = #lines])* }
+}
+
+pub fn generate_const(struct_value: &GenericStruct, options: &StructOptions) -> TokenStream {
+    let const_name = ident(&options.real_const_name());
+    let struct_name = ident(&struct_value.struct_name);
+    let value = struct_value_tokens(struct_value, options);
+
+    quote! {
+        pub const #const_name: #struct_name = #value;
+    }
+}
 
-        if derived_traits.is_empty() {
-            String::new()
+fn derive_tokens(
+    derived_traits: &[String],
+    serde_support: SerdeSupport,
+    use_serde_derive_crate: bool,
+) -> TokenStream {
+    let mut derived_traits = derived_traits
+        .iter()
+        .map(|path| path_tokens(path))
+        .collect::<Vec<TokenStream>>();
+
+    if let Some((ser, de)) = serde_support.should_derive_ser_de() {
+        let prefix = if use_serde_derive_crate {
+            quote!(serde_derive)
         } else {
-            format!("#[derive({})]\n", derived_traits.join(", "))
+            quote!(serde)
+        };
+
+        if ser {
+            derived_traits.push(quote!(#prefix::Serialize));
+        }
+        if de {
+            derived_traits.push(quote!(#prefix::Deserialize));
+        }
+    }
+
+    if derived_traits.is_empty() {
+        TokenStream::new()
+    } else {
+        quote!(#[derive(#(#derived_traits),*)])
+    }
+}
+
+/// Tokens for a path such as `serde::Serialize`, which has already been
+/// checked by `validation::valid_path`.
+fn path_tokens(path: &str) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    for (index, segment) in path.trim().split("::").enumerate() {
+        if index > 0 {
+            tokens.extend(quote!(::));
+        }
+        if !segment.trim().is_empty() {
+            ident(segment.trim()).to_tokens(&mut tokens);
+        }
+    }
+    tokens
+}
+
+pub fn generate_enum(variants: &[String], options: &EnumOptions) -> TokenStream {
+    let enum_name = ident(&options.enum_name);
+    let variant_names = variants
+        .iter()
+        .map(|variant| ident(variant))
+        .collect::<Vec<Ident>>();
+
+    let derive = derive_tokens(
+        &options.derived_traits,
+        options.serde_support,
+        options.use_serde_derive_crate,
+    );
+
+    let mut tokens = quote! {
+        #derive
+        pub enum #enum_name {
+            #(#variant_names,)*
         }
     };
 
-    output.push_str(&format!(
-        "{}#[allow(non_camel_case_types, non_snake_case, clippy::struct_excessive_bools, clippy::struct_field_names)]
-pub struct {} {{
-{}
-}}
-",
-        derive_string,
-        struct_value.struct_name,
-        field_strings.join("\n")
-    ));
-
-    // TODO: is this ... accurate? Does this handle nested arrays/options???
-    for value in struct_value.fields.values() {
-        match *value {
-            GenericValue::Struct(ref value) => generate_struct_declarations(output, value, options),
-            GenericValue::Array(ref values) => {
-                if let Some(GenericValue::Struct(value)) = values.get(0) {
-                    generate_struct_declarations(output, value, options);
+    if let Some(all_variants_const) = &options.all_variants_const {
+        let all_variants_const = ident(all_variants_const);
+        tokens.extend(quote! {
+            impl #enum_name {
+                pub const #all_variants_const: &'static [#enum_name] = &[#(#enum_name::#variant_names),*];
+            }
+        });
+    }
+
+    if let (true, Some(first_variant)) = (options.first_variant_is_default, variant_names.first()) {
+        tokens.extend(quote! {
+            impl ::std::default::Default for #enum_name {
+                fn default() -> Self {
+                    Self::#first_variant
                 }
             }
-            _ => (),
-        }
+        });
     }
+
+    if options.impl_display {
+        tokens.extend(quote! {
+            impl ::std::fmt::Display for #enum_name {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                    write!(f, "{:?}", self)
+                }
+            }
+        });
+    }
+
+    if let (true, Some(all_variants_const)) = (options.impl_from_str, &options.all_variants_const) {
+        let all_variants_const = ident(all_variants_const);
+        tokens.extend(quote! {
+            impl ::std::str::FromStr for #enum_name {
+                type Err = ();
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    const STRINGS: &'static [&'static str] = &[#(#variants),*];
+                    for (index, &key) in STRINGS.iter().enumerate() {
+                        if key == s {
+                            return Ok(#enum_name::#all_variants_const[index]);
+                        }
+                    }
+                    Err(())
+                }
+            }
+        });
+    }
+
+    tokens
 }
 
-// TODO: Shouldn't really need to be public
-pub fn type_string(value: &GenericValue) -> String {
-    type_string_with_options(value, 0)
+pub fn generate_enum_data(
+    data: &[GenericValue],
+    enum_options: &EnumOptions,
+    struct_options: &StructOptions,
+) -> TokenStream {
+    let mut tokens = TokenStream::new();
+
+    let Some(data_type) = array_element_type(data) else {
+        return tokens;
+    };
+
+    generate_nested_struct_declarations(
+        &mut tokens,
+        &mut BTreeSet::new(),
+        &data_type,
+        struct_options,
+    );
+
+    let enum_name = ident(&enum_options.enum_name);
+    let data_type = type_tokens(&data_type, struct_options.max_array_size);
+    let data_values = data.iter().map(|value| value_tokens(value, struct_options));
+
+    tokens.extend(quote! {
+        impl #enum_name {
+            const DATA: &'static [#data_type] = &[#(#data_values),*];
+
+            pub const fn data(self) -> &'static #data_type {
+                &Self::DATA[self as usize]
+            }
+        }
+    });
+
+    tokens
 }
 
-#[allow(clippy::get_first)]
-// TODO: So ugly, wow.
-fn type_string_with_options(value: &GenericValue, max_array_size: usize) -> String {
+/// A readable name for the type of a value, used to compare the types of
+/// values while validating and unifying them.
+pub fn type_string(value: &GenericValue) -> String {
     match *value {
         GenericValue::Unit => "()".to_owned(),
         GenericValue::Bool(_) => "bool".to_owned(),
@@ -91,6 +329,7 @@ fn type_string_with_options(value: &GenericValue, max_array_size: usize) -> Stri
         GenericValue::I16(_) => "i16".to_owned(),
         GenericValue::I32(_) => "i32".to_owned(),
         GenericValue::I64(_) => "i64".to_owned(),
+        GenericValue::I128(_) => "i128".to_owned(),
         GenericValue::U8(_) => "u8".to_owned(),
         GenericValue::U16(_) => "u16".to_owned(),
         GenericValue::U32(_) => "u32".to_owned(),
@@ -102,106 +341,147 @@ fn type_string_with_options(value: &GenericValue, max_array_size: usize) -> Stri
         GenericValue::String(_) => "Cow<'static, str>".to_owned(),
         GenericValue::Option(ref value) => {
             let element_type = match *value {
-                Some(ref value) => type_string_with_options(value, max_array_size),
-                None => type_string_with_options(&GenericValue::Unit, max_array_size),
+                Some(ref value) => type_string(value),
+                None => type_string(&GenericValue::Unit),
             };
             format!("Option<{element_type}>")
         }
         GenericValue::Array(ref values) => {
-            let element_type = match values.get(0) {
-                Some(element) => type_string_with_options(element, max_array_size),
-                None => type_string_with_options(&GenericValue::Unit, max_array_size),
+            let element_type = match array_element_type(values) {
+                Some(element) => type_string(&element),
+                None => type_string(&GenericValue::Unit),
+            };
+            format!("Cow<'static, [{element_type}]>")
+        }
+        GenericValue::Struct(ref struct_value) => struct_value.struct_name.clone(),
+    }
+}
+
+fn array_element_type(values: &[GenericValue]) -> Option<GenericValue> {
+    match parsing::unified_type("", values) {
+        Ok(element) => element,
+        Err(_) => values.first().cloned(),
+    }
+}
+
+fn type_tokens(value: &GenericValue, max_array_size: usize) -> TokenStream {
+    match *value {
+        GenericValue::Unit => quote!(()),
+        GenericValue::Bool(_) => quote!(bool),
+        GenericValue::Char(_) => quote!(char),
+        GenericValue::I8(_) => quote!(i8),
+        GenericValue::I16(_) => quote!(i16),
+        GenericValue::I32(_) => quote!(i32),
+        GenericValue::I64(_) => quote!(i64),
+        GenericValue::I128(_) => quote!(i128),
+        GenericValue::U8(_) => quote!(u8),
+        GenericValue::U16(_) => quote!(u16),
+        GenericValue::U32(_) => quote!(u32),
+        GenericValue::U64(_) => quote!(u64),
+        GenericValue::ISize(_) => quote!(isize),
+        GenericValue::Usize(_) => quote!(usize),
+        GenericValue::F32(_) => quote!(f32),
+        GenericValue::F64(_) => quote!(f64),
+        GenericValue::String(_) => quote!(::std::borrow::Cow<'static, str>),
+        GenericValue::Option(ref value) => {
+            let element_type = match *value {
+                Some(ref value) => type_tokens(value, max_array_size),
+                None => type_tokens(&GenericValue::Unit, max_array_size),
+            };
+            quote!(Option<#element_type>)
+        }
+        GenericValue::Array(ref values) => {
+            let element_type = match array_element_type(values) {
+                Some(element) => type_tokens(&element, max_array_size),
+                None => type_tokens(&GenericValue::Unit, max_array_size),
             };
             if !values.is_empty() && values.len() <= max_array_size {
-                format!("[{}; {}]", element_type, values.len())
+                let length = Literal::usize_unsuffixed(values.len());
+                quote!([#element_type; #length])
             } else {
-                format!("Cow<'static, [{element_type}]>")
+                quote!(::std::borrow::Cow<'static, [#element_type]>)
             }
         }
-        GenericValue::Struct(ref struct_value) => struct_value.struct_name.clone(),
+        GenericValue::Struct(ref struct_value) => {
+            ident(&struct_value.struct_name).into_token_stream()
+        }
     }
 }
 
-#[allow(clippy::arithmetic_side_effects)]
-fn value_string(value: &GenericValue, indentation: usize, max_array_size: usize) -> String {
+fn value_tokens(value: &GenericValue, options: &StructOptions) -> TokenStream {
     match *value {
-        GenericValue::Unit => "()".to_string(),
-        GenericValue::Bool(value) => value.to_string(),
-        GenericValue::Char(value) => format!("'{value}'"),
-        GenericValue::I8(value) => value.to_string(),
-        GenericValue::I16(value) => value.to_string(),
-        GenericValue::I32(value) => value.to_string(),
-        GenericValue::I64(value) => value.to_string(),
-        GenericValue::U8(value) => value.to_string(),
-        GenericValue::U16(value) => value.to_string(),
-        GenericValue::U32(value) => value.to_string(),
-        GenericValue::U64(value) => value.to_string(),
-        GenericValue::ISize(value) => value.to_string(),
-        GenericValue::Usize(value) => value.to_string(),
-        GenericValue::F32(value) => float_string(value),
-        GenericValue::F64(value) => float_string(value),
-        GenericValue::String(ref value) => format!("Cow::Borrowed(\"{value}\")"),
+        GenericValue::Unit => quote!(()),
+        GenericValue::Bool(value) => value.into_token_stream(),
+        GenericValue::Char(value) => Literal::character(value).into_token_stream(),
+        GenericValue::I8(value) => Literal::i8_unsuffixed(value).into_token_stream(),
+        GenericValue::I16(value) => Literal::i16_unsuffixed(value).into_token_stream(),
+        GenericValue::I32(value) => Literal::i32_unsuffixed(value).into_token_stream(),
+        GenericValue::I64(value) => Literal::i64_unsuffixed(value).into_token_stream(),
+        GenericValue::I128(value) => Literal::i128_unsuffixed(value).into_token_stream(),
+        GenericValue::U8(value) => Literal::u8_unsuffixed(value).into_token_stream(),
+        GenericValue::U16(value) => Literal::u16_unsuffixed(value).into_token_stream(),
+        GenericValue::U32(value) => Literal::u32_unsuffixed(value).into_token_stream(),
+        GenericValue::U64(value) => Literal::u64_unsuffixed(value).into_token_stream(),
+        GenericValue::ISize(value) => Literal::isize_unsuffixed(value).into_token_stream(),
+        GenericValue::Usize(value) => Literal::usize_unsuffixed(value).into_token_stream(),
+        GenericValue::F32(value) if value.is_finite() => {
+            Literal::f32_unsuffixed(value).into_token_stream()
+        }
+        GenericValue::F64(value) if value.is_finite() => {
+            Literal::f64_unsuffixed(value).into_token_stream()
+        }
+        GenericValue::F32(value) => non_finite_tokens(f64::from(value), quote!(f32)),
+        GenericValue::F64(value) => non_finite_tokens(value, quote!(f64)),
+        GenericValue::String(ref value) => {
+            let value = Literal::string(value);
+            quote!(::std::borrow::Cow::Borrowed(#value))
+        }
         GenericValue::Option(ref value) => match *value {
             Some(ref value) => {
-                format!("Some({})", value_string(value, indentation, max_array_size))
+                let value = value_tokens(value, options);
+                quote!(Some(#value))
             }
-            None => "None".to_string(),
+            None => quote!(None),
         },
         GenericValue::Array(ref values) => {
-            let value_strings = values
+            let values = values
                 .iter()
-                .map(|value| value_string(value, indentation + 4, max_array_size))
-                .collect::<Vec<String>>();
+                .map(|value| value_tokens(value, options))
+                .collect::<Vec<TokenStream>>();
 
-            if !values.is_empty() && values.len() <= max_array_size {
-                format!("[{}]", value_strings.join(", "))
+            if !values.is_empty() && values.len() <= options.max_array_size {
+                quote!([#(#values),*])
             } else {
-                format!("Cow::Borrowed(&[{}])", value_strings.join(", "))
+                quote!(::std::borrow::Cow::Borrowed(&[#(#values),*]))
             }
         }
-        GenericValue::Struct(ref struct_value) => {
-            struct_value_string(struct_value, indentation, max_array_size)
-        }
+        GenericValue::Struct(ref struct_value) => struct_value_tokens(struct_value, options),
     }
 }
 
-#[allow(clippy::arithmetic_side_effects)]
-pub fn struct_value_string(
-    value: &GenericStruct,
-    indentation: usize,
-    max_array_size: usize,
-) -> String {
-    let values = value
-        .fields
-        .iter()
-        .map(|(field, value)| {
-            format!(
-                "{:indent$}{}: {},\n",
-                "",
-                field.to_case(Case::Snake),
-                value_string(value, indentation + 4, max_array_size),
-                indent = indentation + 4
-            )
-        })
-        .collect::<Vec<String>>();
-    format!(
-        "{} {{\n{}{:indent$}}}",
-        value.struct_name,
-        values.join(""),
-        "",
-        indent = indentation
-    )
+fn non_finite_tokens(value: f64, float_type: TokenStream) -> TokenStream {
+    if value.is_nan() {
+        quote!(#float_type::NAN)
+    } else if value.is_sign_positive() {
+        quote!(#float_type::INFINITY)
+    } else {
+        quote!(#float_type::NEG_INFINITY)
+    }
 }
 
-fn float_string<T>(float: T) -> String
-where
-    T: ToString + Copy,
-{
-    let mut result = float.to_string();
-    if !result.contains('.') {
-        result.push_str(".0");
+fn struct_value_tokens(value: &GenericStruct, options: &StructOptions) -> TokenStream {
+    let struct_name = ident(&value.struct_name);
+    let field_names = value.fields.keys().map(|key| field_ident(key, options));
+    let field_values = value
+        .fields
+        .values()
+        .map(|value| value_tokens(value, options));
+
+    quote! {
+        #struct_name {
+            #(#field_names: #field_values,)*
+        }
     }
-    result
 }
 
 #[cfg(test)]
@@ -210,61 +490,314 @@ mod tests {
 
     const U: GenericValue = GenericValue::Unit;
 
+    fn array_options(max_array_size: usize) -> StructOptions {
+        StructOptions {
+            max_array_size,
+            ..StructOptions::default()
+        }
+    }
+
+    fn assert_tokens(actual: TokenStream, expected: TokenStream) {
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
+
+    fn assert_contains(actual: &TokenStream, expected: TokenStream) {
+        let (actual, expected) = (actual.to_string(), expected.to_string());
+        assert!(actual.contains(&expected), "`{expected}` not in `{actual}`");
+    }
+
     #[test]
     fn empty_slice_type() {
-        assert_eq!(
-            type_string_with_options(&GenericValue::Array(vec![]), 0),
-            "Cow<'static, [()]>",
+        assert_tokens(
+            type_tokens(&GenericValue::Array(vec![]), 0),
+            quote!(::std::borrow::Cow<'static, [()]>),
         );
 
-        assert_eq!(
-            type_string_with_options(&GenericValue::Array(vec![]), 4),
-            "Cow<'static, [()]>",
+        assert_tokens(
+            type_tokens(&GenericValue::Array(vec![]), 4),
+            quote!(::std::borrow::Cow<'static, [()]>),
         );
     }
 
     #[test]
     fn non_empty_slice_type() {
-        assert_eq!(
-            type_string_with_options(&GenericValue::Array(vec![U, U, U]), 0),
-            "Cow<'static, [()]>",
+        assert_tokens(
+            type_tokens(&GenericValue::Array(vec![U, U, U]), 0),
+            quote!(::std::borrow::Cow<'static, [()]>),
         );
     }
 
     #[test]
     fn non_empty_array_type() {
-        assert_eq!(
-            type_string_with_options(&GenericValue::Array(vec![U, U, U]), 4),
-            "[(); 3]",
+        assert_tokens(
+            type_tokens(&GenericValue::Array(vec![U, U, U]), 4),
+            quote!([(); 3]),
         );
     }
 
     #[test]
     fn empty_slice_value() {
-        assert_eq!(
-            value_string(&GenericValue::Array(vec![]), 0, 0),
-            "Cow::Borrowed(&[])",
+        assert_tokens(
+            value_tokens(&GenericValue::Array(vec![]), &array_options(0)),
+            quote!(::std::borrow::Cow::Borrowed(&[])),
         );
 
-        assert_eq!(
-            value_string(&GenericValue::Array(vec![]), 0, 4),
-            "Cow::Borrowed(&[])",
+        assert_tokens(
+            value_tokens(&GenericValue::Array(vec![]), &array_options(4)),
+            quote!(::std::borrow::Cow::Borrowed(&[])),
         );
     }
 
     #[test]
     fn non_empty_slice_value() {
-        assert_eq!(
-            value_string(&GenericValue::Array(vec![U, U, U]), 0, 0),
-            "Cow::Borrowed(&[(), (), ()])",
+        assert_tokens(
+            value_tokens(&GenericValue::Array(vec![U, U, U]), &array_options(0)),
+            quote!(::std::borrow::Cow::Borrowed(&[(), (), ()])),
         );
     }
 
     #[test]
     fn non_empty_array_value() {
-        assert_eq!(
-            value_string(&GenericValue::Array(vec![U, U, U]), 0, 4),
-            "[(), (), ()]",
+        assert_tokens(
+            value_tokens(&GenericValue::Array(vec![U, U, U]), &array_options(4)),
+            quote!([(), (), ()]),
+        );
+    }
+
+    #[test]
+    fn number_values() {
+        assert_tokens(
+            value_tokens(&GenericValue::I64(-5), &StructOptions::default()),
+            quote!(-5),
+        );
+        assert_tokens(
+            value_tokens(&GenericValue::U64(u64::MAX), &StructOptions::default()),
+            quote!(18446744073709551615),
+        );
+        assert_tokens(
+            value_tokens(&GenericValue::F64(2.0), &StructOptions::default()),
+            quote!(2.0),
+        );
+        assert_tokens(
+            value_tokens(&GenericValue::F32(0.5), &StructOptions::default()),
+            quote!(0.5),
+        );
+        assert_tokens(
+            value_tokens(&GenericValue::F64(f64::NAN), &StructOptions::default()),
+            quote!(f64::NAN),
+        );
+        assert_tokens(
+            value_tokens(
+                &GenericValue::F32(f32::NEG_INFINITY),
+                &StructOptions::default(),
+            ),
+            quote!(f32::NEG_INFINITY),
+        );
+    }
+
+    #[test]
+    fn derived_trait_paths() {
+        assert_tokens(
+            derive_tokens(
+                &["Debug".to_owned(), "::std::hash::Hash".to_owned()],
+                SerdeSupport::Yes,
+                false,
+            ),
+            quote!(#[derive(Debug, ::std::hash::Hash, serde::Serialize, serde::Deserialize)]),
+        );
+        assert_tokens(derive_tokens(&[], SerdeSupport::No, false), quote!());
+    }
+
+    #[test]
+    fn struct_and_const() {
+        let mut fields = linear_map::LinearMap::new();
+        fields.insert("maxSize".to_owned(), GenericValue::I64(1));
+        let config = GenericStruct {
+            struct_name: "Config".to_owned(),
+            fields,
+            ..GenericStruct::default()
+        };
+        let options = StructOptions::default();
+
+        assert_tokens(
+            generate_structs(&config, &options),
+            quote! {
+                #[derive(Debug, Clone)]
+                #[allow(non_camel_case_types, non_snake_case, clippy::struct_excessive_bools, clippy::struct_field_names)]
+                pub struct Config {
+                    pub max_size: i64,
+                }
+            },
+        );
+        assert_tokens(
+            generate_const(&config, &options),
+            quote! {
+                pub const CONFIG: Config = Config { max_size: 1, };
+            },
+        );
+    }
+
+    #[test]
+    fn enum_with_all_impls() {
+        let variants = vec!["ItemOne".to_owned(), "ItemTwo".to_owned()];
+        let tokens = generate_enum(&variants, &EnumOptions::default());
+
+        assert_contains(
+            &tokens,
+            quote!(
+                pub enum Key {
+                    ItemOne,
+                    ItemTwo,
+                }
+            ),
+        );
+        assert_contains(
+            &tokens,
+            quote!(
+                pub const ALL: &'static [Key] = &[Key::ItemOne, Key::ItemTwo];
+            ),
+        );
+        assert_contains(&tokens, quote!(Self::ItemOne));
+        assert_contains(&tokens, quote!(impl ::std::fmt::Display for Key));
+        assert_contains(&tokens, quote!(&["ItemOne", "ItemTwo"]));
+    }
+
+    #[test]
+    fn enum_without_optional_impls() {
+        let variants = vec!["ItemOne".to_owned()];
+        let tokens = generate_enum(
+            &variants,
+            &EnumOptions {
+                all_variants_const: None,
+                derived_traits: vec![],
+                first_variant_is_default: false,
+                impl_display: false,
+                impl_from_str: false,
+                ..EnumOptions::default()
+            },
+        );
+
+        assert_tokens(
+            tokens,
+            quote!(
+                pub enum Key {
+                    ItemOne,
+                }
+            ),
         );
     }
+
+    #[test]
+    fn empty_enum_has_no_default() {
+        let tokens = generate_enum(&[], &EnumOptions::default());
+
+        assert_contains(
+            &tokens,
+            quote!(
+                pub enum Key {}
+            ),
+        );
+        assert!(!tokens.to_string().contains("Default for"));
+    }
+
+    #[test]
+    fn enum_data_lookup() {
+        let data = vec![GenericValue::I64(1), GenericValue::I64(2)];
+        let tokens = generate_enum_data(&data, &EnumOptions::default(), &StructOptions::default());
+
+        assert_contains(
+            &tokens,
+            quote!(
+                const DATA: &'static [i64] = &[1, 2];
+            ),
+        );
+        assert_contains(
+            &tokens,
+            quote!(
+                pub const fn data(self) -> &'static i64 {
+                    &Self::DATA[self as usize]
+                }
+            ),
+        );
+
+        let data = vec![
+            GenericValue::Option(None),
+            GenericValue::Option(Some(Box::new(GenericValue::I64(2)))),
+        ];
+        let tokens = generate_enum_data(&data, &EnumOptions::default(), &StructOptions::default());
+
+        assert_contains(
+            &tokens,
+            quote! {
+                const DATA: &'static [Option<i64>] = &[None, Some(2)];
+            },
+        );
+    }
+
+    #[test]
+    fn escaped_string_values() {
+        let value = |s: &str| {
+            value_tokens(
+                &GenericValue::String(s.to_owned()),
+                &StructOptions::default(),
+            )
+        };
+
+        assert_tokens(
+            value("plain"),
+            quote!(::std::borrow::Cow::Borrowed("plain")),
+        );
+        assert_tokens(
+            value(r#"say "hi""#),
+            quote!(::std::borrow::Cow::Borrowed("say \"hi\"")),
+        );
+        assert_tokens(
+            value(r"C:\path"),
+            quote!(::std::borrow::Cow::Borrowed("C:\\path")),
+        );
+        assert_tokens(
+            value("a\nb\tc\r\0"),
+            quote!(::std::borrow::Cow::Borrowed("a\nb\tc\r\0")),
+        );
+        assert_tokens(
+            value("bell\u{7}"),
+            quote!(::std::borrow::Cow::Borrowed("bell\u{7}")),
+        );
+        assert_tokens(
+            value("accénts 😇"),
+            quote!(::std::borrow::Cow::Borrowed("accénts 😇")),
+        );
+    }
+
+    #[test]
+    fn escaped_char_values() {
+        let value =
+            |c: char| value_tokens(&GenericValue::Char(c), &StructOptions::default()).to_string();
+
+        assert_eq!(value('a'), "'a'");
+        assert_eq!(value('\''), r"'\''");
+        assert_eq!(value('"'), "'\"'");
+        assert_eq!(value('\\'), r"'\\'");
+        assert_eq!(value('\n'), r"'\n'");
+        assert_eq!(value('é'), "'é'");
+    }
+
+    #[test]
+    fn rendered_items() {
+        let code = render_items(quote!(
+            pub const A: i64 = 1;
+        ));
+
+        assert_eq!(code, "#[allow(dead_code)]\npub const A: i64 = 1;\n");
+    }
+
+    #[test]
+    fn rendered_file() {
+        let code = render_file(quote!(
+            pub const A: i64 = 1;
+        ));
+
+        assert!(code.starts_with("#![cfg_attr(rustfmt, rustfmt_skip)]\n#![allow(dead_code)]\n"));
+        assert!(code.contains("pub const A: i64 = 1;\n"));
+    }
 }
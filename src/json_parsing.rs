@@ -1,4 +1,4 @@
-use super::cperror::GenerationError;
+use super::cperror::{DeserializationError, GenerationError};
 use super::options::StructOptions;
 use super::parsing;
 use super::value::{GenericStruct, GenericValue};
@@ -7,60 +7,130 @@ use serde_json::{self, Value};
 pub fn parse_json(json: &str, options: &StructOptions) -> Result<GenericStruct, GenerationError> {
     use parsing::ParsedFields;
 
-    let json_struct: ParsedFields<Value> = serde_json::from_str(json)
-        .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+    let json_struct: ParsedFields<Value> =
+        serde_json::from_str(json).map_err(|err| json_error(err, json))?;
 
-    let generic_struct = parsing::parsed_to_generic_struct(json_struct, options, json_to_raw_value);
+    let generic_struct =
+        parsing::parsed_to_generic_struct(json_struct, options, json_to_raw_value)?;
 
     Ok(generic_struct)
 }
 
-fn json_to_raw_value(
+pub fn json_to_raw_value(
     super_struct: &str,
     super_key: &str,
+    key_path: &str,
     value: Value,
     options: &StructOptions,
-) -> GenericValue {
-    match value {
+) -> Result<GenericValue, GenerationError> {
+    let value = match value {
         Value::Null => GenericValue::Option(None),
         Value::Bool(value) => GenericValue::Bool(value),
         Value::Number(value) => match (value.as_i64(), value.as_u64(), value.as_f64()) {
-            // TODO: Add some unit tests for this
             (Some(x), _, _) => parsing::preferred_int(x, options.default_int_size),
             (None, Some(x), _) => GenericValue::U64(x),
             (None, None, Some(x)) => parsing::preferred_float(x, options.default_float_size),
-            _ => unimplemented!("Should handle error here"), // TODO
+            _ => {
+                return Err(GenerationError::UnsupportedNumber {
+                    key_path: key_path.to_owned(),
+                    number: value.to_string(),
+                })
+            }
         },
         Value::String(value) => GenericValue::String(value),
         Value::Array(values) => GenericValue::Array(
             values
                 .into_iter()
-                .map(|value| json_to_raw_value(super_struct, super_key, value, options))
-                .collect(),
+                .enumerate()
+                .map(|(index, value)| {
+                    let element_path = parsing::element_path(key_path, index);
+                    json_to_raw_value(super_struct, super_key, &element_path, value, options)
+                })
+                .collect::<Result<_, _>>()?,
         ),
         Value::Object(values) => {
             let sub_struct_name = format!("{super_struct}__{super_key}");
-            let values: std::collections::BTreeMap<String, GenericValue> = values
+            let values = values
                 .into_iter()
                 .map(|(key, value)| {
-                    let value = json_to_raw_value(&sub_struct_name, &key, value, options);
-                    (key, value)
+                    let field_path = parsing::field_path(key_path, &key);
+                    let value =
+                        json_to_raw_value(&sub_struct_name, &key, &field_path, value, options)?;
+                    Ok((key, value))
                 })
-                .collect();
+                .collect::<Result<_, GenerationError>>()?;
             GenericValue::Struct(GenericStruct {
                 struct_name: sub_struct_name,
                 fields: values,
+                ..GenericStruct::default()
             })
         }
-    }
+    };
+
+    Ok(value)
 }
 
-#[allow(dead_code)]
 pub fn parse_map_keys(json: &str) -> Result<Vec<String>, GenerationError> {
     use linear_map::LinearMap;
 
-    let map: LinearMap<String, Value> = serde_json::from_str(json)
-        .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+    let map: LinearMap<String, Value> =
+        serde_json::from_str(json).map_err(|err| json_error(err, json))?;
 
     Ok(map.into_iter().map(|pair| pair.0).collect())
 }
+
+/// Converts a JSON parse error, pointing at its location in the config.
+pub fn json_error(error: serde_json::Error, json: &str) -> GenerationError {
+    let line_column = (error.line(), error.column());
+    DeserializationError::from_parser(error, json, Some(line_column)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::type_string;
+
+    fn parse_and_unify(json: &str) -> GenericStruct {
+        let mut generic_struct = parse_json(json, &StructOptions::default()).unwrap();
+        parsing::unify_struct(&mut generic_struct).unwrap();
+        generic_struct
+    }
+
+    #[test]
+    fn number_types() {
+        let parsed = parse_and_unify(r#"{"int": -5, "big": 18446744073709551615, "float": 0.5}"#);
+
+        assert_eq!(type_string(&parsed.fields["int"]), "i64");
+        assert_eq!(type_string(&parsed.fields["big"]), "u64");
+        assert_eq!(type_string(&parsed.fields["float"]), "f64");
+    }
+
+    #[test]
+    fn mixed_number_arrays() {
+        let parsed = parse_and_unify(
+            r#"{
+                "floats": [1, 2.5],
+                "unsigned": [1, 18446744073709551615],
+                "wide": [-1, 18446744073709551615],
+                "optional": [null, 1]
+            }"#,
+        );
+
+        assert_eq!(type_string(&parsed.fields["floats"]), "Cow<'static, [f64]>");
+        assert_eq!(
+            type_string(&parsed.fields["unsigned"]),
+            "Cow<'static, [u64]>"
+        );
+        assert_eq!(type_string(&parsed.fields["wide"]), "Cow<'static, [i128]>");
+        assert_eq!(
+            type_string(&parsed.fields["optional"]),
+            "Cow<'static, [Option<i64>]>"
+        );
+    }
+
+    #[test]
+    fn incompatible_arrays() {
+        let mut parsed = parse_json(r#"{"mixed": [1, "one"]}"#, &StructOptions::default()).unwrap();
+        assert!(parsing::unify_struct(&mut parsed).is_err());
+    }
+}
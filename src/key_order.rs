@@ -0,0 +1,159 @@
+use std::fmt;
+
+use linear_map::LinearMap;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use super::value::{GenericStruct, GenericValue};
+
+/// The order in which keys appear in a config document, for the formats
+/// whose parsers keep maps sorted by key.
+///
+/// This mirrors the shape of the document: each map records its keys in
+/// document order along with the order of any maps nested inside them, and
+/// each sequence records the order of its elements' keys.
+#[derive(Debug, Default)]
+pub struct KeyOrder {
+    keys: Vec<(String, KeyOrder)>,
+    elements: Vec<KeyOrder>,
+}
+
+impl KeyOrder {
+    /// Reorders the fields of the struct, and of every struct nested inside
+    /// it, to match this key order.
+    ///
+    /// Fields which do not appear in the key order are kept after the others.
+    pub fn apply(&self, struct_value: &mut GenericStruct) {
+        let mut remaining: Vec<(String, GenericValue)> =
+            std::mem::take(&mut struct_value.fields).into();
+        let mut fields = LinearMap::with_capacity(remaining.len());
+
+        for (key, order) in &self.keys {
+            if let Some(index) = remaining.iter().position(|(field, _)| field == key) {
+                let (key, mut value) = remaining.remove(index);
+                order.apply_to_value(&mut value);
+                fields.insert(key, value);
+            }
+        }
+        fields.extend(remaining);
+
+        struct_value.fields = fields;
+    }
+
+    fn apply_to_value(&self, value: &mut GenericValue) {
+        match value {
+            GenericValue::Struct(struct_value) => self.apply(struct_value),
+            GenericValue::Array(values) => {
+                for (value, order) in values.iter_mut().zip(&self.elements) {
+                    order.apply_to_value(value);
+                }
+            }
+            GenericValue::Option(Some(value)) => self.apply_to_value(value),
+            _ => (),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyOrder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(KeyOrderVisitor)
+    }
+}
+
+struct KeyOrderVisitor;
+
+impl<'de> Visitor<'de> for KeyOrderVisitor {
+    type Value = KeyOrder;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any config value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<KeyOrder, A::Error> {
+        let mut keys = Vec::new();
+        while let Some(Key(key)) = map.next_key()? {
+            keys.push((key, map.next_value()?));
+        }
+        Ok(KeyOrder {
+            keys,
+            elements: Vec::new(),
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<KeyOrder, A::Error> {
+        let mut elements = Vec::new();
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+        Ok(KeyOrder {
+            keys: Vec::new(),
+            elements,
+        })
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<KeyOrder, D::Error> {
+        KeyOrder::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<KeyOrder, D::Error> {
+        KeyOrder::deserialize(deserializer)
+    }
+
+    fn visit_none<E>(self) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+
+    fn visit_unit<E>(self) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+
+    fn visit_char<E>(self, _: char) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<KeyOrder, E> {
+        Ok(KeyOrder::default())
+    }
+}
+
+/// A map key, deserialized as an identifier because RON only supports
+/// identifiers for the field names of its structs.
+struct Key(String);
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Key;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_str<E>(self, key: &str) -> Result<Key, E> {
+        Ok(Key(key.to_owned()))
+    }
+}
@@ -26,10 +26,10 @@
 //!
 //! ```rust,no_run
 //! // build.rs
-//! use config_struct::{Error, StructOptions};
+//! use config_to_struct::{Error, StructOptions};
 //!
 //! fn main() -> Result<(), Error> {
-//!     config_struct::create_struct(
+//!     config_to_struct::create_struct(
 //!         "config.toml",
 //!         "src/config.rs",
 //!         &StructOptions::default())
@@ -53,19 +53,22 @@
 //! ```rust,no_run
 //! // config.rs
 //! // ...
-//! use std::borrow::Cow;
-//!
 //! #[derive(Debug, Clone)]
 //! #[allow(non_camel_case_types)]
 //! pub struct Config {
-//!     pub features: Cow<'static, [Cow<'static, str>]>,
-//!     pub name: Cow<'static, str>,
+//!     pub features: ::std::borrow::Cow<'static, [::std::borrow::Cow<'static, str>]>,
+//!     pub name: ::std::borrow::Cow<'static, str>,
 //!     pub version: i64,
 //! }
-//!
 //! pub const CONFIG: Config = Config {
-//!     features: Cow::Borrowed(&[Cow::Borrowed("one"), Cow::Borrowed("two"), Cow::Borrowed("three")]),
-//!     name: Cow::Borrowed("Application"),
+//!     features: ::std::borrow::Cow::Borrowed(
+//!         &[
+//!             ::std::borrow::Cow::Borrowed("one"),
+//!             ::std::borrow::Cow::Borrowed("two"),
+//!             ::std::borrow::Cow::Borrowed("three"),
+//!         ],
+//!     ),
+//!     name: ::std::borrow::Cow::Borrowed("Application"),
 //!     version: 5,
 //! };
 //! ```
@@ -74,14 +77,32 @@
 //! the entire Config struct to be either heap allocated at runtime,
 //! or a compile time constant, as shown above.
 //!
+//! In TOML and YAML configs, a comment directly above a key becomes the doc
+//! comment of its field, and a comment at the top of the file which is
+//! followed by a blank line documents the generated module and struct.
+//!
+//! A base config can be combined with environment-specific overlays using
+//! [`create_struct_from_layers`](fn.create_struct_from_layers.html), which
+//! deep-merges the files in order, so that `config.dev.toml` only needs the
+//! keys which differ from `config.toml`.
+//!
+//! To keep generated code out of your source tree, use
+//! [`create_struct_in_out_dir`](fn.create_struct_in_out_dir.html), or
+//! [`create_struct_from_layers_in_out_dir`](fn.create_struct_from_layers_in_out_dir.html)
+//! for a layered config, instead and include the result:
+//!
+//! ```rust,ignore
+//! include!(concat!(env!("OUT_DIR"), "/config.rs"));
+//! ```
+//!
 //! ## Enums
 //!
 //! ```rust,no_run
 //! // build.rs
-//! use config_struct::{Error, EnumOptions};
+//! use config_to_struct::{Error, EnumOptions};
 //!
 //! fn main() -> Result<(), Error> {
-//!     config_struct::create_enum(
+//!     config_to_struct::create_enum(
 //!         "items.yaml",
 //!         "src/items.rs",
 //!         &EnumOptions::default())
@@ -89,7 +110,7 @@
 //! ```
 //!
 //! The above build script will take the following `items.yaml` file and generate
-//! a (not-formatted) `items.rs` like the following:
+//! an `items.rs` like the following:
 //!
 //! ```yaml
 //! # items.yaml
@@ -111,17 +132,17 @@
 //! impl Key {
 //!     pub const ALL: &'static [Key] = &[Key::ItemOne, Key::ItemTwo];
 //! }
-//! impl Default for Key {
+//! impl ::std::default::Default for Key {
 //!     fn default() -> Self {
 //!         Self::ItemOne
 //!     }
 //! }
-//! impl std::fmt::Display for Key {
-//!     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+//! impl ::std::fmt::Display for Key {
+//!     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
 //!         write!(f, "{:?}", self)
 //!     }
 //! }
-//! impl std::str::FromStr for Key {
+//! impl ::std::str::FromStr for Key {
 //!     type Err = ();
 //!     fn from_str(s: &str) -> Result<Self, Self::Err> {
 //!         const STRINGS: &'static [&'static str] = &["ItemOne", "ItemTwo"];
@@ -139,6 +160,104 @@
 //! which could be disabled in the `EnumOptions`. The intended purpose of
 //! this is to have a small efficient type to use as a key into the data stored
 //! in the initial config file.
+//!
+//! If you set `data_struct_name` in the `EnumOptions`, the data under each key
+//! is also turned into a struct, and a lookup function is generated:
+//!
+//! ```rust,no_run
+//! // build.rs
+//! use config_to_struct::{Error, EnumOptions};
+//!
+//! fn main() -> Result<(), Error> {
+//!     config_to_struct::create_enum(
+//!         "items.yaml",
+//!         "src/items.rs",
+//!         &EnumOptions {
+//!             data_struct_name: Some("ItemData".to_owned()),
+//!             ..EnumOptions::default()
+//!         })
+//! }
+//! ```
+//!
+//! ```rust,no_run
+//! // items.rs
+//! // ...
+//! # #[derive(Clone, Copy)]
+//! # pub enum Key { ItemOne, ItemTwo }
+//! impl Key {
+//!     const DATA: &'static [::std::borrow::Cow<
+//!         'static,
+//!         [::std::borrow::Cow<'static, str>],
+//!     >] = &[
+//!         ::std::borrow::Cow::Borrowed(&[::std::borrow::Cow::Borrowed("data")]),
+//!         ::std::borrow::Cow::Borrowed(
+//!             &[::std::borrow::Cow::Borrowed("more"), ::std::borrow::Cow::Borrowed("data")],
+//!         ),
+//!     ];
+//!     pub const fn data(
+//!         self,
+//!     ) -> &'static ::std::borrow::Cow<'static, [::std::borrow::Cow<'static, str>]> {
+//!         &Self::DATA[self as usize]
+//!     }
+//! }
+//! ```
+//!
+//! When the data under each key is a map, a struct named `ItemData` is
+//! generated for it and `data()` returns `&'static ItemData`.
+//!
+//! ## Flighting
+//!
+//! With the `json-parsing` feature, a layer named `*.settingsconfig.json`
+//! is a flighting file. Rather than being merged like the other layers, it
+//! overrides individual settings of the layers before it, by dotted key
+//! path, and may group overrides into flights which can be turned off:
+//!
+//! ```json
+//! {
+//!     "settings": { "database.pool_size": 8 },
+//!     "flights": [
+//!         { "name": "BigCache", "enabled": true, "settings": { "cache.size": 1024 } }
+//!     ]
+//! }
+//! ```
+//!
+//! Every overridden setting must already exist in the config with the same
+//! type, so that the generated struct is unchanged by flighting.
+//!
+//! ## Hot reloading
+//!
+//! A dynamically loaded config can be reloaded while the program is running
+//! with a [`ConfigWatcher`](struct.ConfigWatcher.html), which polls the file
+//! at the interval of a [`SynchronizationScope`](enum.SynchronizationScope.html)
+//! and publishes each new value to its handles and subscribers:
+//!
+//! ```rust,ignore
+//! let watcher = config_to_struct::ConfigWatcher::with_scope(
+//!     "config.toml",
+//!     config_to_struct::SynchronizationScope::Prod,
+//!     |path| Ok(Config::load_from(path)?.into_owned()))?;
+//!
+//! let updates = watcher.subscribe();
+//! let config = watcher.handle();
+//! ```
+//!
+//! ## Token streams
+//!
+//! For procedural macros and other code generators,
+//! [`generate_struct_tokens`](fn.generate_struct_tokens.html) returns the
+//! generated items as a `proc_macro2::TokenStream` instead of a string. All
+//! paths in the output are fully qualified, so it can be spliced into any
+//! module.
+//!
+//! The companion `config_struct_macros` crate uses this to provide an
+//! `include_config!` macro, for crates which would rather not have a
+//! `build.rs` file. The macro is not re-exported from this crate, since
+//! `config_struct_macros` depends on it, so add `config_struct_macros` as a
+//! dependency and call the macro from there:
+//!
+//! ```rust,ignore
+//! config_struct_macros::include_config!("config.toml", struct_name = "Config");
+//! ```
 
 #[cfg(feature = "json-parsing")]
 mod json_parsing;
@@ -152,19 +271,32 @@ mod toml_parsing;
 #[cfg(feature = "yaml-parsing")]
 mod yaml_parsing;
 
+mod enums;
 #[cfg(feature = "experimental-files-enum")]
 pub mod files_enum;
 mod structs;
 
+#[cfg(any(feature = "toml-parsing", feature = "yaml-parsing"))]
+mod comments;
 mod cperror;
+mod dedup;
 mod files;
+#[cfg(feature = "json-parsing")]
+mod flighting;
 mod format;
 mod generation;
+#[cfg(any(feature = "ron-parsing", feature = "toml-parsing"))]
+mod key_order;
 mod load_fns;
+mod naming;
 mod options;
 mod parsing;
+mod synchronization;
+#[cfg(test)]
+mod temp_dir;
 mod validation;
 mod value;
+mod watch;
 
 #[cfg(not(any(
     feature = "json-parsing",
@@ -175,8 +307,16 @@ mod value;
 compile_error!("The config_struct crate requires at least one parsing feature to be enabled:\n {json-parsing, ron-parsing, toml-parsing, yaml-parsing}");
 
 pub use crate::{
-    cperror::{Error, GenerationError, OptionsError},
+    cperror::{
+        DeserializationError, Diagnostic, Error, GenerationError, OptionsError, SourceLocation,
+        SynchronizationError, ValidationReport,
+    },
+    enums::*,
     format::Format,
-    options::{DynamicLoading, FloatSize, IntSize, SerdeSupport, StructOptions, EnumOptions},
+    options::{
+        ConfigParserOptions, DynamicLoading, Edition, EnumOptions, FloatSize, IntSize,
+        SerdeSupport, StructNaming, StructOptions, SynchronizationScope,
+    },
     structs::*,
+    watch::{ConfigHandle, ConfigWatcher},
 };
@@ -1,53 +1,402 @@
+use std::collections::BTreeSet;
 use std::path::Path;
 
+use proc_macro2::TokenStream;
+use quote::quote;
+
 use super::format::Format;
+use super::generation::{field_ident, ident};
+use super::naming;
+use super::options::StructOptions;
+use super::parsing;
+use super::value::{GenericStruct, GenericValue};
 
-pub fn dynamic_load_impl(format: Format, struct_name: &str, filepath: &Path) -> String {
+pub fn dynamic_load_impl(
+    format: Format,
+    struct_name: &str,
+    filepaths: &[&Path],
+    env_prefix: Option<&str>,
+) -> TokenStream {
     let load_expression = match format {
         #[cfg(feature = "json-parsing")]
-        Format::Json => "::serde_json::from_str(&file_contents)",
+        Format::Json => quote!(::serde_json::from_str(&file_contents)),
+
+        #[cfg(feature = "ron-parsing")]
+        Format::Ron => quote!(::ron::de::from_str(&file_contents)),
+
+        #[cfg(feature = "toml-parsing")]
+        Format::Toml => quote!(::toml::from_str(&file_contents)),
+
+        #[cfg(feature = "yaml-parsing")]
+        Format::Yaml => quote!(::serde_yaml::from_str(&file_contents)),
+    };
+
+    let expect_message = format!("Failed to load {struct_name}.");
+    let struct_name = ident(struct_name);
+    let (binding, apply_overrides) = match env_prefix {
+        Some(prefix) => {
+            let prefix = format!("{prefix}_");
+            (
+                quote!(mut result),
+                quote!(result.apply_env_overrides(#prefix)?;),
+            )
+        }
+        None => (quote!(result), quote!()),
+    };
+
+    if let [filepath] = filepaths {
+        let filepath = filepath_tokens(filepath);
+        return quote! {
+            impl #struct_name {
+                pub fn load() -> ::std::borrow::Cow<'static, Self> {
+                    let filepath = #filepath;
+                    Self::load_from(filepath.as_ref()).expect(#expect_message)
+                }
+
+                pub fn load_from(filepath: &::std::path::Path) -> Result<::std::borrow::Cow<'static, Self>, Box<dyn ::std::error::Error>> {
+                    let file_contents = ::std::fs::read_to_string(filepath)?;
+                    let #binding: Self = #load_expression?;
+                    #apply_overrides
+                    Ok(::std::borrow::Cow::Owned(result))
+                }
+            }
+        };
+    }
+
+    let filepaths = filepaths.iter().map(|filepath| filepath_tokens(filepath));
+    let (value_type, map_variant, from_value) = match format {
+        #[cfg(feature = "json-parsing")]
+        Format::Json => (
+            quote!(::serde_json::Value),
+            quote!(Object),
+            quote!(::serde_json::from_value(merged)),
+        ),
 
         #[cfg(feature = "ron-parsing")]
-        Format::Ron => "::ron::de::from_str(&file_contents)",
+        Format::Ron => (
+            quote!(::ron::value::Value),
+            quote!(Map),
+            quote!(merged.into_rust()),
+        ),
 
         #[cfg(feature = "toml-parsing")]
-        Format::Toml => "::toml::from_str(&file_contents)",
+        Format::Toml => (
+            quote!(::toml::Value),
+            quote!(Table),
+            quote!(merged.try_into()),
+        ),
 
         #[cfg(feature = "yaml-parsing")]
-        Format::Yaml => "::serde_yaml::from_str(&file_contents)",
+        Format::Yaml => (
+            quote!(::serde_yaml::Value),
+            quote!(Mapping),
+            quote!(::serde_yaml::from_value(merged)),
+        ),
+    };
+
+    quote! {
+        impl #struct_name {
+            pub fn load() -> ::std::borrow::Cow<'static, Self> {
+                let filepaths = [#(#filepaths),*];
+                let filepaths = filepaths.map(::std::path::Path::new);
+                Self::load_from_layers(&filepaths).expect(#expect_message)
+            }
+
+            pub fn load_from(filepath: &::std::path::Path) -> Result<::std::borrow::Cow<'static, Self>, Box<dyn ::std::error::Error>> {
+                Self::load_from_layers(&[filepath])
+            }
+
+            pub fn load_from_layers(filepaths: &[&::std::path::Path]) -> Result<::std::borrow::Cow<'static, Self>, Box<dyn ::std::error::Error>> {
+                fn merge(base: &mut #value_type, layer: #value_type) {
+                    match (base, layer) {
+                        (#value_type::#map_variant(base), #value_type::#map_variant(layer)) => {
+                            for (key, value) in layer {
+                                match base.get_mut(&key) {
+                                    Some(base) => merge(base, value),
+                                    None => {
+                                        base.insert(key, value);
+                                    }
+                                }
+                            }
+                        }
+                        (base, layer) => *base = layer,
+                    }
+                }
+
+                let mut merged = None;
+                for filepath in filepaths {
+                    let file_contents = ::std::fs::read_to_string(filepath)?;
+                    let layer: #value_type = #load_expression?;
+                    match &mut merged {
+                        Some(merged) => merge(merged, layer),
+                        None => merged = Some(layer),
+                    }
+                }
+
+                let merged = merged.ok_or("No config files to load.")?;
+                let #binding: Self = #from_value?;
+                #apply_overrides
+                Ok(::std::borrow::Cow::Owned(result))
+            }
+        }
+    }
+}
+
+/// The path of a config file, relative to the crate being built unless it
+/// is absolute.
+fn filepath_tokens(filepath: &Path) -> TokenStream {
+    if filepath.is_absolute() {
+        let filepath = filepath.display().to_string();
+        quote!(#filepath)
+    } else {
+        let filepath = format!("/{}", filepath.display());
+        quote!(concat!(env!("CARGO_MANIFEST_DIR"), #filepath))
+    }
+}
+
+pub fn static_load_impl(
+    struct_name: &str,
+    const_name: &str,
+    env_prefix: Option<&str>,
+    layered: bool,
+) -> TokenStream {
+    let expect_message = format!("Failed to load {struct_name}.");
+    let struct_name = ident(struct_name);
+    let const_name = ident(const_name);
+
+    let layered_impl = layered.then(|| {
+        quote! {
+            #[inline(always)]
+            pub fn load_from_layers(_: &[&::std::path::Path]) -> Result<::std::borrow::Cow<'static, Self>, Box<dyn ::std::error::Error>> {
+                Self::load_from(::std::path::Path::new(""))
+            }
+        }
+    });
+
+    if let Some(prefix) = env_prefix {
+        let prefix = format!("{prefix}_");
+        return quote! {
+            impl #struct_name {
+                pub fn load() -> ::std::borrow::Cow<'static, Self> {
+                    Self::load_from(::std::path::Path::new("")).expect(#expect_message)
+                }
+
+                pub fn load_from(_: &::std::path::Path) -> Result<::std::borrow::Cow<'static, Self>, Box<dyn ::std::error::Error>> {
+                    let mut result = ::std::borrow::Cow::Borrowed(&#const_name);
+                    if ::std::env::vars_os().any(|(name, _)| name.to_string_lossy().starts_with(#prefix)) {
+                        result.to_mut().apply_env_overrides(#prefix)?;
+                    }
+                    Ok(result)
+                }
+
+                #layered_impl
+            }
+        };
+    }
+
+    quote! {
+        impl #struct_name {
+            #[inline(always)]
+            pub fn load() -> ::std::borrow::Cow<'static, Self> {
+                ::std::borrow::Cow::Borrowed(&#const_name)
+            }
+
+            #[inline(always)]
+            pub fn load_from(_: &::std::path::Path) -> Result<::std::borrow::Cow<'static, Self>, Box<dyn ::std::error::Error>> {
+                Ok(::std::borrow::Cow::Borrowed(&#const_name))
+            }
+
+            #layered_impl
+        }
+    }
+}
+
+/// Generates an `apply_env_overrides` method for the struct and every struct
+/// nested inside it, which replaces the value of each field with the value of
+/// its environment variable, if it is set.
+pub fn env_overrides_impl(struct_value: &GenericStruct, options: &StructOptions) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    env_overrides_impls(&mut tokens, &mut BTreeSet::new(), struct_value, options);
+    tokens
+}
+
+fn env_overrides_impls(
+    output: &mut TokenStream,
+    declared: &mut BTreeSet<String>,
+    struct_value: &GenericStruct,
+    options: &StructOptions,
+) {
+    if !declared.insert(struct_value.struct_name.clone()) {
+        return;
+    }
+
+    let struct_name = ident(&struct_value.struct_name);
+    let overrides = struct_value
+        .fields
+        .iter()
+        .filter_map(|(key, value)| field_override(key, value, options));
+
+    output.extend(quote! {
+        impl #struct_name {
+            #[allow(unused_variables)]
+            pub fn apply_env_overrides(&mut self, prefix: &str) -> Result<(), Box<dyn ::std::error::Error>> {
+                #[allow(dead_code)]
+                fn var(name: &str) -> Result<Option<String>, String> {
+                    match ::std::env::var(name) {
+                        Ok(value) => Ok(Some(value)),
+                        Err(::std::env::VarError::NotPresent) => Ok(None),
+                        Err(::std::env::VarError::NotUnicode(_)) => {
+                            Err(format!("The environment variable {name} is not valid unicode."))
+                        }
+                    }
+                }
+
+                #[allow(dead_code)]
+                fn parse<T>(name: &str, value: &str) -> Result<T, String>
+                where
+                    T: ::std::str::FromStr,
+                    T::Err: ::std::fmt::Display,
+                {
+                    value.parse().map_err(|err| {
+                        format!("Failed to parse the environment variable {name}={value:?}: {err}")
+                    })
+                }
+
+                #(#overrides)*
+                Ok(())
+            }
+        }
+    });
+
+    for value in struct_value.fields.values() {
+        nested_env_overrides_impls(output, declared, value, options);
+    }
+}
+
+fn nested_env_overrides_impls(
+    output: &mut TokenStream,
+    declared: &mut BTreeSet<String>,
+    value: &GenericValue,
+    options: &StructOptions,
+) {
+    match value {
+        GenericValue::Struct(struct_value) => {
+            env_overrides_impls(output, declared, struct_value, options)
+        }
+        GenericValue::Array(values) => {
+            if let Ok(Some(value)) = parsing::unified_type("", values) {
+                nested_env_overrides_impls(output, declared, &value, options);
+            }
+        }
+        GenericValue::Option(Some(value)) => {
+            nested_env_overrides_impls(output, declared, value, options)
+        }
+        _ => (),
+    }
+}
+
+/// The code overriding a single field, if its type can be overridden.
+fn field_override(key: &str, value: &GenericValue, options: &StructOptions) -> Option<TokenStream> {
+    let field_name = field_ident(key, options);
+    let var_name = naming::field_name(key, options).to_uppercase();
+
+    let tokens = match value {
+        GenericValue::Struct(_) => quote! {
+            self.#field_name.apply_env_overrides(&format!("{prefix}{}__", #var_name))?;
+        },
+        GenericValue::Option(Some(value)) => match **value {
+            GenericValue::Struct(_) => quote! {
+                if let Some(value) = &mut self.#field_name {
+                    value.apply_env_overrides(&format!("{prefix}{}__", #var_name))?;
+                }
+            },
+            GenericValue::String(_) => quote! {
+                let name = format!("{prefix}{}", #var_name);
+                if let Some(value) = var(&name)? {
+                    self.#field_name = Some(::std::borrow::Cow::Owned(value));
+                }
+            },
+            GenericValue::Unit | GenericValue::Option(_) | GenericValue::Array(_) => return None,
+            _ => quote! {
+                let name = format!("{prefix}{}", #var_name);
+                if let Some(value) = var(&name)? {
+                    self.#field_name = Some(parse(&name, &value)?);
+                }
+            },
+        },
+        GenericValue::String(_) => quote! {
+            let name = format!("{prefix}{}", #var_name);
+            if let Some(value) = var(&name)? {
+                self.#field_name = ::std::borrow::Cow::Owned(value);
+            }
+        },
+        GenericValue::Unit | GenericValue::Option(None) | GenericValue::Array(_) => return None,
+        _ => quote! {
+            let name = format!("{prefix}{}", #var_name);
+            if let Some(value) = var(&name)? {
+                self.#field_name = parse(&name, &value)?;
+            }
+        },
     };
 
-    format!(
-        r#"impl {struct_name} {{
-    pub fn load() -> Cow<'static, Self> {{
-        let filepath = concat!(env!("CARGO_MANIFEST_DIR"), "/{filepath}");
-        Self::load_from(filepath.as_ref()).expect("Failed to load {struct_name}.")
-    }}
-
-    pub fn load_from(filepath: &::std::path::Path) -> Result<Cow<'static, Self>, Box<dyn ::std::error::Error>> {{
-        let file_contents = ::std::fs::read_to_string(filepath)?;
-        let result: Self = {load_expression}?;
-        Ok(Cow::Owned(result))
-    }}
-}}"#,
-        struct_name = struct_name,
-        filepath = filepath.display(),
-        load_expression = load_expression
-    )
-}
-
-pub fn static_load_impl(struct_name: &str, const_name: &str) -> String {
-    format!(
-        r#"impl {struct_name} {{
-    #[inline(always)]
-    pub fn load() -> Cow<'static, Self> {{
-        Cow::Borrowed(&{const_name})
-    }}
-
-    #[inline(always)]
-    pub fn load_from(_: &::std::path::Path) -> Result<Cow<'static, Self>, Box<dyn ::std::error::Error>> {{
-        Ok(Cow::Borrowed(&{const_name}))
-    }}
-}}"#
-    )
+    Some(tokens)
+}
+
+#[cfg(all(test, feature = "toml-parsing"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_filepath() {
+        let tokens = dynamic_load_impl(Format::Toml, "Config", &[Path::new("config.toml")], None);
+        let expected = quote! {
+            let filepath = concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml");
+        };
+
+        assert!(tokens.to_string().contains(&expected.to_string()));
+    }
+
+    #[test]
+    fn absolute_filepath() {
+        let filepath = std::env::temp_dir().join("config.toml");
+        let tokens = dynamic_load_impl(Format::Toml, "Config", &[&filepath], None);
+        let filepath = filepath.display().to_string();
+        let expected = quote! {
+            let filepath = #filepath;
+        };
+
+        assert!(tokens.to_string().contains(&expected.to_string()));
+    }
+
+    #[test]
+    fn env_overrides() {
+        let source = "port = 80\nname = 'a'\nports = [1]\n[database]\npool_size = 4\n";
+        let config = crate::toml_parsing::parse_toml(source, &Default::default()).unwrap();
+        let tokens = env_overrides_impl(&config, &Default::default()).to_string();
+
+        let expected = [
+            quote! { self.port = parse(&name, &value)?; },
+            quote! { self.name = ::std::borrow::Cow::Owned(value); },
+            quote! { self.database.apply_env_overrides(&format!("{prefix}{}__", "DATABASE"))?; },
+            quote! { let name = format!("{prefix}{}", "POOL_SIZE"); },
+        ];
+        for expected in expected {
+            assert!(tokens.contains(&expected.to_string()), "{expected}");
+        }
+        assert!(!tokens.contains("PORTS"));
+    }
+
+    #[test]
+    fn env_prefix() {
+        let expected = quote! { result.apply_env_overrides("APP_")?; };
+
+        let tokens = dynamic_load_impl(Format::Toml, "Config", &[Path::new("a.toml")], Some("APP"));
+        assert!(tokens.to_string().contains(&expected.to_string()));
+        let tokens = static_load_impl("Config", "CONFIG", Some("APP"), false);
+        let expected = quote! { result.to_mut().apply_env_overrides("APP_")?; };
+        assert!(tokens.to_string().contains(&expected.to_string()));
+
+        let tokens = static_load_impl("Config", "CONFIG", None, false);
+        assert!(!tokens.to_string().contains("apply_env_overrides"));
+    }
 }
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+
+use convert_case::{Case, Casing};
+
+use super::cperror::{GenerationError, OptionsError};
+use super::dedup;
+use super::options::StructOptions;
+use super::validation;
+use super::value::{GenericStruct, GenericValue};
+
+/// The name of the struct field generated for a config key.
+///
+/// With `sanitize_keys` the name may be a keyword, which has to be written
+/// as a raw identifier.
+pub fn field_name(key: &str, options: &StructOptions) -> String {
+    if options.sanitize_keys {
+        sanitize_key(key).unwrap_or_else(|| key.to_owned())
+    } else {
+        key.to_case(Case::Snake)
+    }
+}
+
+/// Converts a key into a snake_case identifier, or `None` if the key has no
+/// ASCII letters or digits to make one from.
+///
+/// Runs of other characters become single underscores, names starting with
+/// a digit get a leading underscore, and keywords which cannot be raw
+/// identifiers get a trailing one.
+pub fn sanitize_key(key: &str) -> Option<String> {
+    let snake = key.to_case(Case::Snake);
+    let words: Vec<&str> = snake
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut name = words.join("_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if !validation::can_be_raw(&name) {
+        name.push('_');
+    }
+    Some(name)
+}
+
+/// Names every nested struct in the config according to the naming options.
+///
+/// Fails if a name is not a valid identifier, or if two structs with
+/// different fields would be given the same name.
+pub fn name_structs(
+    root: &mut GenericStruct,
+    options: &StructOptions,
+) -> Result<(), GenerationError> {
+    for (key, value) in &mut root.fields {
+        name_value(value, &mut vec![key.clone()], options);
+    }
+
+    let mut shapes = BTreeMap::new();
+    shapes.insert(
+        root.struct_name.clone(),
+        dedup::shape(&GenericValue::Struct(root.clone())),
+    );
+    for value in root.fields.values() {
+        check_names(value, &mut shapes, options)?;
+    }
+
+    Ok(())
+}
+
+fn name_value(value: &mut GenericValue, path: &mut Vec<String>, options: &StructOptions) {
+    match value {
+        GenericValue::Struct(struct_value) => {
+            struct_value.struct_name = options.nested_struct_name(path);
+            for (key, field) in &mut struct_value.fields {
+                path.push(key.clone());
+                name_value(field, path, options);
+                path.pop();
+            }
+        }
+        GenericValue::Array(values) => {
+            for value in values {
+                name_value(value, path, options);
+            }
+        }
+        GenericValue::Option(Some(value)) => name_value(value, path, options),
+        _ => (),
+    }
+}
+
+fn check_names(
+    value: &GenericValue,
+    shapes: &mut BTreeMap<String, String>,
+    options: &StructOptions,
+) -> Result<(), GenerationError> {
+    match value {
+        GenericValue::Struct(struct_value) => {
+            let name = &struct_value.struct_name;
+            if !validation::valid_identifier(name, options.edition) {
+                return Err(OptionsError::InvalidStructName(name.clone()).into());
+            }
+
+            let shape = dedup::shape(value);
+            match shapes.get(name) {
+                Some(existing) if *existing != shape => {
+                    return Err(GenerationError::DuplicateStructName(name.clone()));
+                }
+                Some(_) => (),
+                None => {
+                    shapes.insert(name.clone(), shape);
+                }
+            }
+
+            for field in struct_value.fields.values() {
+                check_names(field, shapes, options)?;
+            }
+        }
+        GenericValue::Array(values) => {
+            if let Ok(Some(element)) = super::parsing::unified_type("", values) {
+                check_names(&element, shapes, options)?;
+            }
+        }
+        GenericValue::Option(Some(value)) => check_names(value, shapes, options)?,
+        _ => (),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::StructNaming;
+
+    fn table(fields: Vec<(&str, GenericValue)>) -> GenericValue {
+        GenericValue::Struct(GenericStruct {
+            struct_name: String::new(),
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+            ..GenericStruct::default()
+        })
+    }
+
+    fn config() -> GenericStruct {
+        let pool = table(vec![("size", GenericValue::I64(4))]);
+        match table(vec![("database", table(vec![("pool", pool)]))]) {
+            GenericValue::Struct(mut root) => {
+                root.struct_name = "Config".to_owned();
+                root
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn pool_name(config: &GenericStruct) -> &str {
+        match &config.fields["database"] {
+            GenericValue::Struct(database) => match &database.fields["pool"] {
+                GenericValue::Struct(pool) => &pool.struct_name,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn named(options: &StructOptions) -> Result<GenericStruct, GenerationError> {
+        let mut config = config();
+        name_structs(&mut config, options)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn naming_strategies() {
+        let with_naming = |struct_naming| StructOptions {
+            struct_naming,
+            ..StructOptions::default()
+        };
+
+        let config = named(&with_naming(StructNaming::Underscored)).unwrap();
+        assert_eq!(pool_name(&config), "_Config__database__pool");
+
+        let config = named(&with_naming(StructNaming::PascalCaseKeyPath)).unwrap();
+        assert_eq!(pool_name(&config), "DatabasePool");
+
+        let config = named(&with_naming(StructNaming::PascalCaseKey)).unwrap();
+        assert_eq!(pool_name(&config), "Pool");
+
+        let config = named(&with_naming(StructNaming::Custom(|path| {
+            format!("{}Settings", path.concat())
+        })))
+        .unwrap();
+        assert_eq!(pool_name(&config), "databasepoolSettings");
+    }
+
+    #[test]
+    fn underscored_names_use_the_struct_name() {
+        let options = StructOptions {
+            struct_name: "App".to_owned(),
+            ..StructOptions::default()
+        };
+
+        let config = named(&options).unwrap();
+        assert_eq!(pool_name(&config), "_App__database__pool");
+    }
+
+    #[test]
+    fn name_overrides() {
+        let mut options = StructOptions::default();
+        options
+            .struct_name_overrides
+            .insert("database.pool".to_owned(), "PoolConfig".to_owned());
+
+        let config = named(&options).unwrap();
+        assert_eq!(pool_name(&config), "PoolConfig");
+    }
+
+    #[test]
+    fn conflicting_names() {
+        let options = StructOptions {
+            struct_naming: StructNaming::Custom(|_| "Same".to_owned()),
+            ..StructOptions::default()
+        };
+        assert!(named(&options).is_err());
+    }
+
+    #[test]
+    fn invalid_names() {
+        let options = StructOptions {
+            struct_naming: StructNaming::Custom(|_| "not valid".to_owned()),
+            ..StructOptions::default()
+        };
+        assert!(named(&options).is_err());
+    }
+
+    #[test]
+    fn sanitized_keys() {
+        let sanitize = |key: &str| sanitize_key(key);
+        assert_eq!(sanitize("max-connections").unwrap(), "max_connections");
+        assert_eq!(sanitize("Content-Type").unwrap(), "content_type");
+        assert_eq!(sanitize("maxSize").unwrap(), "max_size");
+        assert_eq!(sanitize("a.b  c").unwrap(), "a_b_c");
+        assert_eq!(sanitize("2fa").unwrap(), "_2_fa");
+        assert_eq!(sanitize("type").unwrap(), "type");
+        assert_eq!(sanitize("Self").unwrap(), "self_");
+        assert_eq!(sanitize("crate").unwrap(), "crate_");
+        assert_eq!(sanitize("-*-"), None);
+        assert_eq!(sanitize(""), None);
+
+        let options = StructOptions {
+            sanitize_keys: true,
+            ..StructOptions::default()
+        };
+        assert_eq!(field_name("max-connections", &options), "max_connections");
+        assert_eq!(field_name("maxSize", &StructOptions::default()), "max_size");
+    }
+}
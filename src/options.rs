@@ -1,6 +1,11 @@
-use super::cperror::OptionsError;
+use super::cperror::{OptionsError, SynchronizationError};
 use super::format::Format;
+use super::synchronization;
 use super::validation;
+use convert_case::{Case, Casing};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Options for serde support.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -56,6 +61,61 @@ impl Default for DynamicLoading {
     }
 }
 
+/// How to name the structs generated for nested tables in the config.
+///
+/// Each nested struct is identified by the path of keys leading to it
+/// from the root of the config. For example, the `[database.pool]`
+/// table in a TOML config has the key path `["database", "pool"]`.
+/// The elements of an array share the key path of the array itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum StructNaming {
+    /// Join the struct name and the key path with double underscores,
+    /// for example `_Config__database__pool`.
+    #[default]
+    Underscored,
+
+    /// Convert the whole key path to `PascalCase`, for example
+    /// `DatabasePool`.
+    PascalCaseKeyPath,
+
+    /// Convert only the last key to `PascalCase`, for example
+    /// `Pool`.
+    PascalCaseKey,
+
+    /// Call a function with the key path to get the name.
+    Custom(fn(&[String]) -> String),
+}
+
+impl PartialEq for StructNaming {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for StructNaming {}
+
+/// The Rust edition that the generated code is compiled with, which
+/// decides which names are keywords.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edition {
+    /// Rust 2015.
+    Rust2015,
+
+    /// Rust 2018, which adds the `async`, `await`, `dyn` and `try`
+    /// keywords.
+    Rust2018,
+
+    /// Rust 2021, which has the same keywords as Rust 2018.
+    Rust2021,
+
+    /// Rust 2024, which adds the `gen` keyword. This is the default.
+    #[default]
+    Rust2024,
+}
+
 /// Represents a floating-point type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FloatSize {
@@ -73,83 +133,82 @@ pub enum IntSize {
     ISize,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How often a synchronized config is checked for changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SynchronizationScope {
+    /// Poll every 30 seconds, so that changes show up quickly in tests.
     Test,
+
+    /// Poll every 15 minutes.
     Prod,
 }
 
+impl SynchronizationScope {
+    /// The interval at which to poll the config for changes.
+    pub fn polling_interval(self) -> Duration {
+        match self {
+            Self::Test => Duration::from_secs(30),
+            Self::Prod => Duration::from_secs(15 * 60),
+        }
+    }
+}
+
 impl Default for SynchronizationScope {
     fn default() -> Self {
         Self::Prod
     }
 }
 
-/// Config parser options
-#[allow(clippy::struct_excessive_bools)]
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Options for locating the synchronization file of an app.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ConfigParserOptions {
-    /// The app name to be used to resolve the path to the syncronization file. If not provided, synchronization will be skipped
+    /// The name of the app, used to name its synchronization file. Only
+    /// ASCII letters, digits, `_`, `-` and `.` are allowed.
     pub app_name: String,
 
-    /// Supports synchronization for both test and prod. Test scope will typically provide a polling interval of less than 45 secs.
-    /// Prod scope will typically provide a polling interval of not less than 15 minutes.
+    /// Which synchronization file to use.
+    ///
+    /// Defaults to `None`, which skips synchronization.
     pub synchronization_scope: Option<SynchronizationScope>,
+
+    /// The base directory for synchronization files in the test scope.
+    ///
+    /// Defaults to `None`, which uses a directory under the system temp
+    /// directory.
+    pub test_directory: Option<PathBuf>,
+
+    /// The base directory for synchronization files in the prod scope.
+    ///
+    /// Defaults to `None`, which uses the user's config directory:
+    /// `$XDG_CONFIG_HOME` (falling back to `~/.config`) on Linux,
+    /// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows.
+    pub prod_directory: Option<PathBuf>,
 }
 
 impl ConfigParserOptions {
-    #[allow(dead_code)]
-    pub fn synchronization_file_path(&self) -> Result<String, OptionsError> {
-        //TODO: Use regex to validate the name, not just relying on length
-        let mut file_path = String::new();
-        let mut path_prefix = String::new();
-        let mut path_suffix = String::new();
-        let mut app_name_str = self.app_name.trim();
-        if app_name_str.is_empty() {
-            app_name_str = "non_watchable_app";
-        }
-
-        match self.synchronization_scope {
-            Some(SynchronizationScope::Prod) => {
-                // TODO: Read path to variant config folder from registry?
-                // Confirm file resolution to the format: [AGENT]_[CLIENT]_[VERSION].settingsconfig.json
-                if app_name_str != "non_watchable_app" {
-                    path_prefix =
-                        String::from(r#"C:\Program Files\Microsoft\Datacenter\Flighting\"#);
-                    path_suffix = format!(r#"Substrate_{app_name_str}_V2.settingsconfig.json"#);
-                }
-            }
-            Some(SynchronizationScope::Test) => {
-                path_prefix = String::from(env!("CARGO_MANIFEST_DIR"));
-                path_suffix =
-                    format!(r#"/src/config/config_parser/resources/sync/{app_name_str}.json"#);
-            }
-            None => {
-                path_prefix.clear();
-                path_suffix.clear();
-            }
-        };
-        file_path.clear();
-        file_path = format!(r#"{path_prefix}{path_suffix}"#);
-        if file_path.is_empty() {
-            return Err(OptionsError::InvalidStructName(app_name_str.to_owned()));
-        }
+    /// The path to the synchronization file of the app, or `None` if no
+    /// synchronization scope is set.
+    ///
+    /// The file is `Substrate_<app_name>_V2.settingsconfig.json` in the
+    /// prod scope and `<app_name>.json` in the test scope, directly inside
+    /// the base directory of the scope.
+    ///
+    /// This only resolves the path. Use
+    /// [`ensure_synchronization_file`](#method.ensure_synchronization_file)
+    /// to create the file.
+    pub fn synchronization_file_path(&self) -> Result<Option<PathBuf>, SynchronizationError> {
+        synchronization::file_path(self, &|name| std::env::var_os(name))
+    }
 
-        match std::fs::OpenOptions::new()
-            .create_new(true)
-            .open(file_path.clone())
-        {
-            Ok(_file) => {
-                println!("File did not exist and was created.");
-            }
-            Err(_error) => {
-                println!("File already exists in the target path.");
-            }
+    /// Resolves the path to the synchronization file like
+    /// [`synchronization_file_path`](#method.synchronization_file_path),
+    /// then creates the file and its directories if they do not exist yet.
+    pub fn ensure_synchronization_file(&self) -> Result<Option<PathBuf>, SynchronizationError> {
+        let filepath = self.synchronization_file_path()?;
+        if let Some(filepath) = &filepath {
+            synchronization::ensure_exists(filepath)?;
         }
-
-        let _result = file_path.clone();
-        Ok(file_path)
+        Ok(filepath)
     }
 }
 
@@ -188,6 +247,10 @@ pub struct StructOptions {
 
     /// Shorthand for generating the Serialize and Deserialize traits.
     ///
+    /// Fields are named by converting their keys to snake_case, so fields
+    /// whose names differ from their keys get a `#[serde(rename)]`
+    /// attribute.
+    ///
     /// Defaults to `No`.
     pub serde_support: SerdeSupport,
 
@@ -220,6 +283,22 @@ pub struct StructOptions {
     /// Defaults to `DebugOnly`.
     pub dynamic_loading: DynamicLoading,
 
+    /// A prefix for environment variables which override values
+    /// in the config when it is loaded by the load functions.
+    ///
+    /// The variable for a field is the prefix, an underscore and
+    /// the field's key path, with the keys in upper snake case and
+    /// separated by double underscores. For example, with a prefix
+    /// of `"APP"` the value of `database.pool_size` is overridden
+    /// by `APP_DATABASE__POOL_SIZE`. Each variable is parsed as
+    /// the type of its field; arrays cannot be overridden.
+    ///
+    /// The overrides are applied to a clone of the loaded config, so
+    /// `derived_traits` must include `Clone`.
+    ///
+    /// Defaults to `None`, which disables overrides.
+    pub env_prefix: Option<String>,
+
     /// Whether or not to create the parent directories of the
     /// output file, if they don't exist.
     ///
@@ -237,6 +316,13 @@ pub struct StructOptions {
     /// Defaults to `true`.
     pub write_only_if_changed: bool,
 
+    /// Whether the `create_*` functions should print a
+    /// `cargo:rerun-if-changed` directive for the config file, so
+    /// that the build script reruns whenever the config changes.
+    ///
+    /// Defaults to `false`.
+    pub emit_rerun_if_changed: bool,
+
     /// The type of floating point values in the config, where the
     /// format does not make it explicit.
     ///
@@ -256,15 +342,117 @@ pub struct StructOptions {
     ///
     /// Defaults to `0`.
     pub max_array_size: usize,
+
+    /// Whether to keep the fields of each struct, and of its const,
+    /// in the order their keys appear in the config.
+    ///
+    /// Otherwise the fields are sorted alphabetically by key.
+    ///
+    /// Defaults to `false`.
+    pub preserve_key_order: bool,
+
+    /// Whether to generate a single shared struct for nested
+    /// structs which have exactly the same fields and types.
+    ///
+    /// When all of the identical structs are entries of the same
+    /// table, the shared struct is named (using `struct_naming`)
+    /// after that table's key with any plural `s` removed - so the
+    /// entries of `[servers.a]` and `[servers.b]` will share a
+    /// `_Config__server` struct, or a `Server` struct with
+    /// `PascalCaseKey` naming. Otherwise it is named after the
+    /// first of the identical structs.
+    ///
+    /// Defaults to `false`.
+    pub deduplicate_structs: bool,
+
+    /// Whether to check the whole config before failing, and report
+    /// every invalid key and heterogenous array in it along with its
+    /// key path, instead of only the first.
+    ///
+    /// Defaults to `false`.
+    pub report_all_errors: bool,
+
+    /// Whether to convert config keys which are not valid field names,
+    /// such as `max-connections`, `2fa` or `type`, into snake_case
+    /// identifiers instead of failing. Keywords become raw identifiers
+    /// like `r#type`.
+    ///
+    /// Defaults to `false`.
+    pub sanitize_keys: bool,
+
+    /// Whether to write config keys which are keywords, such as `type`
+    /// or `match`, as raw identifiers like `r#type` instead of failing.
+    /// The path keywords `self`, `Self`, `super` and `crate` can never be
+    /// field names.
+    ///
+    /// Defaults to `false`.
+    pub raw_identifiers: bool,
+
+    /// The edition of the crate that includes the generated code, which
+    /// decides which names are keywords.
+    ///
+    /// Defaults to `Rust2024`, whose keywords include those of every
+    /// earlier edition.
+    pub edition: Edition,
+
+    /// How to name the structs generated for nested tables.
+    ///
+    /// Defaults to `Underscored`.
+    pub struct_naming: StructNaming,
+
+    /// Names to use for specific nested structs instead of the
+    /// `struct_naming` strategy, keyed by the key path joined
+    /// with `.` - for example `"database.pool"`.
+    ///
+    /// Defaults to empty.
+    pub struct_name_overrides: BTreeMap<String, String>,
 }
 
 impl StructOptions {
     pub(crate) fn validate(&self) -> Result<(), OptionsError> {
-        if !validation::valid_identifier(&self.struct_name) {
+        if !validation::valid_identifier(&self.struct_name, self.edition) {
             return Err(OptionsError::InvalidStructName(self.struct_name.clone()));
         }
 
-        Ok(())
+        if let Some(const_name) = &self.const_name {
+            if !validation::valid_identifier(const_name, self.edition) {
+                return Err(OptionsError::InvalidConstName(const_name.clone()));
+            }
+        }
+
+        if self.env_prefix.is_some() && !self.derives_clone() {
+            return Err(OptionsError::EnvPrefixRequiresClone);
+        }
+
+        validate_derived_traits(&self.derived_traits, self.edition)
+    }
+
+    /// Whether `Clone` is one of the derived traits, by name or by path.
+    fn derives_clone(&self) -> bool {
+        self.derived_traits
+            .iter()
+            .any(|derived_trait| derived_trait.rsplit("::").next().map(str::trim) == Some("Clone"))
+    }
+
+    pub(crate) fn nested_struct_name(&self, key_path: &[String]) -> String {
+        if let Some(name) = self.struct_name_overrides.get(&key_path.join(".")) {
+            return name.clone();
+        }
+
+        match self.struct_naming {
+            StructNaming::Underscored => {
+                format!("_{}__{}", self.struct_name, key_path.join("__"))
+            }
+            StructNaming::PascalCaseKeyPath => key_path
+                .iter()
+                .map(|key| key.to_case(Case::Pascal))
+                .collect(),
+            StructNaming::PascalCaseKey => key_path
+                .last()
+                .map(|key| key.to_case(Case::Pascal))
+                .unwrap_or_default(),
+            StructNaming::Custom(name_fn) => name_fn(key_path),
+        }
     }
 
     pub(crate) fn real_const_name(&self) -> String {
@@ -278,7 +466,7 @@ impl StructOptions {
     /// to load the config.
     ///
     /// ```rust
-    /// use config_struct::{StructOptions, SerdeSupport};
+    /// use config_to_struct::{StructOptions, SerdeSupport};
     ///
     /// let options = StructOptions::serde_default();
     ///
@@ -299,7 +487,7 @@ impl StructOptions {
 
 impl Default for StructOptions {
     /// ```rust
-    /// use config_struct::*;
+    /// use config_to_struct::*;
     ///
     /// let default_options = StructOptions {
     ///     format: None,
@@ -314,11 +502,21 @@ impl Default for StructOptions {
     ///     use_serde_derive_crate: false,
     ///     generate_load_fns: false,
     ///     dynamic_loading: DynamicLoading::DebugOnly,
+    ///     env_prefix: None,
     ///     create_dirs: true,
     ///     write_only_if_changed: true,
+    ///     emit_rerun_if_changed: false,
     ///     default_float_size: FloatSize::F64,
     ///     default_int_size: IntSize::I64,
     ///     max_array_size: 0,
+    ///     preserve_key_order: false,
+    ///     deduplicate_structs: false,
+    ///     report_all_errors: false,
+    ///     sanitize_keys: false,
+    ///     raw_identifiers: false,
+    ///     edition: Edition::Rust2024,
+    ///     struct_naming: StructNaming::Underscored,
+    ///     struct_name_overrides: Default::default(),
     /// };
     /// assert_eq!(default_options, StructOptions::default());
     /// ```
@@ -333,11 +531,21 @@ impl Default for StructOptions {
             use_serde_derive_crate: false,
             generate_load_fns: false,
             dynamic_loading: DynamicLoading::DebugOnly,
+            env_prefix: None,
             create_dirs: true,
             write_only_if_changed: true,
+            emit_rerun_if_changed: false,
             default_float_size: FloatSize::F64,
             default_int_size: IntSize::I64,
             max_array_size: 0,
+            preserve_key_order: false,
+            deduplicate_structs: false,
+            report_all_errors: false,
+            sanitize_keys: false,
+            raw_identifiers: false,
+            edition: Edition::default(),
+            struct_naming: StructNaming::default(),
+            struct_name_overrides: BTreeMap::new(),
         }
     }
 }
@@ -426,15 +634,78 @@ pub struct EnumOptions {
     ///
     /// Defaults to `true`.
     pub write_only_if_changed: bool,
+
+    /// Whether the `create_*` functions should print a
+    /// `cargo:rerun-if-changed` directive for the config file, so
+    /// that the build script reruns whenever the config changes.
+    ///
+    /// Defaults to `false`.
+    pub emit_rerun_if_changed: bool,
+
+    /// The name of a struct to generate from the data under each
+    /// key of the config.
+    ///
+    /// If you specify `Some("ItemData")` then the values under
+    /// each key are inferred in the same way as by
+    /// `generate_struct`, and a `const fn data(self) ->
+    /// &'static ItemData` function is generated to look up the
+    /// data for each variant. All variants must have data of the
+    /// same type.
+    ///
+    /// If you specify `None` then the data is ignored.
+    ///
+    /// Defaults to `None`.
+    pub data_struct_name: Option<String>,
+
+    /// The edition of the crate that includes the generated code, which
+    /// decides which names are keywords.
+    ///
+    /// Defaults to `Rust2024`, whose keywords include those of every
+    /// earlier edition.
+    pub edition: Edition,
 }
 
-#[allow(clippy::unnecessary_wraps)]
-#[allow(dead_code)]
 impl EnumOptions {
     pub(crate) fn validate(&self) -> Result<(), OptionsError> {
-        eprintln!("TODO: EnumOptions::validate");
+        if !validation::valid_identifier(&self.enum_name, self.edition) {
+            return Err(OptionsError::InvalidEnumName(self.enum_name.clone()));
+        }
+
+        if let Some(all_variants_const) = &self.all_variants_const {
+            if !validation::valid_identifier(all_variants_const, self.edition) {
+                return Err(OptionsError::InvalidAllVariantsConstName(
+                    all_variants_const.clone(),
+                ));
+            }
+        } else if self.impl_from_str {
+            return Err(OptionsError::FromStrRequiresAllVariantsConst);
+        }
+
+        if let Some(data_struct_name) = &self.data_struct_name {
+            if !validation::valid_identifier(data_struct_name, self.edition) {
+                return Err(OptionsError::InvalidStructName(data_struct_name.clone()));
+            }
+            if *data_struct_name == self.enum_name {
+                return Err(OptionsError::DataStructNameConflict(
+                    data_struct_name.clone(),
+                ));
+            }
+        }
 
-        Ok(())
+        validate_derived_traits(&self.derived_traits, self.edition)
+    }
+
+    pub(crate) fn data_struct_options(&self) -> Option<StructOptions> {
+        self.data_struct_name
+            .as_ref()
+            .map(|data_struct_name| StructOptions {
+                format: self.format,
+                struct_name: data_struct_name.clone(),
+                serde_support: self.serde_support,
+                use_serde_derive_crate: self.use_serde_derive_crate,
+                edition: self.edition,
+                ..StructOptions::default()
+            })
     }
 
     /// The default options plus serde support. This includes
@@ -442,7 +713,7 @@ impl EnumOptions {
     /// to load the config.
     ///
     /// ```rust
-    /// use config_struct::{EnumOptions, SerdeSupport};
+    /// use config_to_struct::{EnumOptions, SerdeSupport};
     ///
     /// let options = EnumOptions::serde_default();
     ///
@@ -463,7 +734,7 @@ impl EnumOptions {
 /// "Eq", "PartialOrd", "Ord", "Hash"]`
 impl Default for EnumOptions {
     /// ```rust
-    /// use config_struct::*;
+    /// use config_to_struct::*;
     ///
     /// let default_options = EnumOptions {
     ///     format: None,
@@ -486,6 +757,9 @@ impl Default for EnumOptions {
     ///     use_serde_derive_crate: false,
     ///     create_dirs: true,
     ///     write_only_if_changed: true,
+    ///     emit_rerun_if_changed: false,
+    ///     data_struct_name: None,
+    ///     edition: Edition::Rust2024,
     /// };
     /// assert_eq!(default_options, EnumOptions::default());
     /// ```
@@ -511,6 +785,21 @@ impl Default for EnumOptions {
             use_serde_derive_crate: false,
             create_dirs: true,
             write_only_if_changed: true,
+            emit_rerun_if_changed: false,
+            data_struct_name: None,
+            edition: Edition::default(),
+        }
+    }
+}
+
+fn validate_derived_traits(
+    derived_traits: &[String],
+    edition: Edition,
+) -> Result<(), OptionsError> {
+    for derived_trait in derived_traits {
+        if !validation::valid_path(derived_trait, edition) {
+            return Err(OptionsError::InvalidDerivedTrait(derived_trait.clone()));
         }
     }
+    Ok(())
 }
@@ -1,30 +1,440 @@
+use super::cperror::GenerationError;
+use super::generation::type_string;
 use super::options::{FloatSize, IntSize, StructOptions};
 use super::value::{GenericStruct, GenericValue};
+use linear_map::LinearMap;
 use std::collections::BTreeMap;
 
-pub type ParsedFields<T> = BTreeMap<String, T>;
+pub type ParsedFields<T> = LinearMap<String, T>;
 
+/// Converts the top-level fields of a parsed config with `convert_fn`,
+/// which is called with the name of the parent struct, the key, the key
+/// path and the value.
 pub fn parsed_to_generic_struct<T, F>(
     parsed_config: ParsedFields<T>,
     options: &StructOptions,
     convert_fn: F,
-) -> GenericStruct
+) -> Result<GenericStruct, GenerationError>
 where
-    F: Fn(&str, &str, T, &StructOptions) -> GenericValue,
+    F: Fn(&str, &str, &str, T, &StructOptions) -> Result<GenericValue, GenerationError>,
 {
-    let struct_name = "Config".to_owned();
+    let struct_name = options.struct_name.clone();
+    let nested_prefix = format!("_{struct_name}");
 
     let fields = parsed_config
         .into_iter()
         .map(|(key, value)| {
-            let value = convert_fn("_Config", &key, value, options);
-            (key, value)
+            let value = convert_fn(&nested_prefix, &key, &key, value, options)?;
+            Ok((key, value))
         })
-        .collect();
+        .collect::<Result<_, GenerationError>>()?;
 
-    GenericStruct {
+    Ok(GenericStruct {
         struct_name,
         fields,
+        ..GenericStruct::default()
+    })
+}
+
+/// The key path of the field `key` of the struct at `path`, such as
+/// `servers.ports`.
+pub fn field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// The key path of the element at `index` of the array at `path`, such as
+/// `servers[3]`.
+pub fn element_path(path: &str, index: usize) -> String {
+    format!("{path}[{index}]")
+}
+
+/// Merges a config layer into `base`. Nested structs are merged key by key,
+/// and any other value in the layer replaces the value under the same key.
+///
+/// Docs from `base` take precedence over docs from the layer.
+pub fn merge_structs(base: &mut GenericStruct, layer: GenericStruct) {
+    for (key, value) in layer.fields {
+        match (base.fields.get_mut(&key), value) {
+            (Some(GenericValue::Struct(base_struct)), GenericValue::Struct(layer_struct)) => {
+                merge_structs(base_struct, layer_struct);
+            }
+            (_, value) => {
+                base.fields.insert(key, value);
+            }
+        }
+    }
+
+    for (key, doc) in layer.field_docs {
+        base.field_docs.entry(key).or_insert(doc);
+    }
+    if base.doc.is_none() {
+        base.doc = layer.doc;
+    }
+}
+
+/// Sorts the fields of the struct, and of every struct nested inside it,
+/// alphabetically by key.
+pub fn sort_fields(struct_value: &mut GenericStruct) {
+    let mut fields: Vec<(String, GenericValue)> = std::mem::take(&mut struct_value.fields).into();
+    fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (_, value) in &mut fields {
+        sort_value_fields(value);
+    }
+
+    struct_value.fields = fields.into_iter().collect();
+}
+
+fn sort_value_fields(value: &mut GenericValue) {
+    match value {
+        GenericValue::Struct(struct_value) => sort_fields(struct_value),
+        GenericValue::Array(values) => values.iter_mut().for_each(sort_value_fields),
+        GenericValue::Option(Some(value)) => sort_value_fields(value),
+        _ => (),
+    }
+}
+
+/// Renames a value's struct type (and every struct nested inside it) to
+/// `struct_name`, naming nested structs `{nested_prefix}__{key}` in the same
+/// way as the parsers do.
+pub fn rename_structs(value: &mut GenericValue, struct_name: &str, nested_prefix: &str) {
+    match value {
+        GenericValue::Struct(struct_value) => {
+            struct_value.struct_name = struct_name.to_owned();
+            for (key, field) in &mut struct_value.fields {
+                let nested_name = format!("{nested_prefix}__{key}");
+                rename_structs(field, &nested_name, &nested_name);
+            }
+        }
+        GenericValue::Array(values) => {
+            for value in values {
+                rename_structs(value, struct_name, nested_prefix);
+            }
+        }
+        GenericValue::Option(Some(value)) => rename_structs(value, struct_name, nested_prefix),
+        _ => (),
+    }
+}
+
+/// Unifies the elements of every array in the struct (recursively) so that
+/// they all have the same type.
+///
+/// Arrays of structs are merged field by field: a field which is missing
+/// from some of the elements becomes an `Option` and is filled with `None`
+/// where it is missing.
+///
+/// Numbers are widened to a common type: integers mixed with floats become
+/// floats, and integers of different types become the first of their own
+/// types, `i64`, `u64` or `i128` which can hold all of the values losslessly.
+/// A `null` mixed with other values makes them all optional.
+pub fn unify_struct(struct_value: &mut GenericStruct) -> Result<(), GenerationError> {
+    for (key, value) in &mut struct_value.fields {
+        unify_value(key, value)?;
+    }
+    Ok(())
+}
+
+fn unify_value(key: &str, value: &mut GenericValue) -> Result<(), GenerationError> {
+    match value {
+        GenericValue::Struct(struct_value) => unify_struct(struct_value)?,
+        GenericValue::Option(Some(value)) => unify_value(key, value)?,
+        GenericValue::Array(values) => unify_values(key, values)?,
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Unifies the given values (and everything nested inside them) so that
+/// they all have the same type.
+pub fn unify_values(key: &str, values: &mut Vec<GenericValue>) -> Result<(), GenerationError> {
+    for value in values.iter_mut() {
+        unify_value(key, value)?;
+    }
+
+    if let Some(unified) = unified_type(key, values)? {
+        *values = std::mem::take(values)
+            .into_iter()
+            .map(|value| coerce(value, &unified))
+            .collect();
+    }
+    Ok(())
+}
+
+/// Finds a value representing the type that all of the given values can be
+/// converted to, or `None` if there are no values.
+///
+/// Only the type of the result is meaningful, not its contents.
+pub fn unified_type(
+    key: &str,
+    values: &[GenericValue],
+) -> Result<Option<GenericValue>, GenerationError> {
+    join_all(values).map_err(|Incompatible| GenerationError::HeterogenousArray(key.into()))
+}
+
+/// Whether the arrays nested at each position of the given values, such as
+/// the `ports` of every server in an array of tables, all have the same
+/// length.
+pub fn uniform_lengths(mut values: Vec<&GenericValue>) -> bool {
+    let mut length = None;
+    let mut elements = Vec::new();
+    let mut fields: BTreeMap<&str, Vec<&GenericValue>> = BTreeMap::new();
+
+    while let Some(value) = values.pop() {
+        match value {
+            GenericValue::Option(Some(value)) => values.push(value),
+            GenericValue::Array(array) => {
+                if *length.get_or_insert(array.len()) != array.len() {
+                    return false;
+                }
+                elements.extend(array);
+            }
+            GenericValue::Struct(struct_value) => {
+                for (key, field) in &struct_value.fields {
+                    fields.entry(key).or_default().push(field);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    (elements.is_empty() || uniform_lengths(elements)) && fields.into_values().all(uniform_lengths)
+}
+
+#[derive(Debug)]
+struct Incompatible;
+
+fn join_all<'a, I>(values: I) -> Result<Option<GenericValue>, Incompatible>
+where
+    I: IntoIterator<Item = &'a GenericValue>,
+{
+    let mut unified: Option<GenericValue> = None;
+    for value in values {
+        unified = Some(match unified {
+            Some(unified) => join(&unified, value)?,
+            None => value.clone(),
+        });
+    }
+    Ok(unified)
+}
+
+fn join(a: &GenericValue, b: &GenericValue) -> Result<GenericValue, Incompatible> {
+    match (a, b) {
+        (GenericValue::Option(None), GenericValue::Option(_)) => Ok(b.clone()),
+        (GenericValue::Option(_), GenericValue::Option(None)) => Ok(a.clone()),
+        (GenericValue::Option(Some(a)), GenericValue::Option(Some(b))) => {
+            Ok(GenericValue::Option(Some(Box::new(join(a, b)?))))
+        }
+        (GenericValue::Option(None), b) | (b, GenericValue::Option(None)) => {
+            Ok(GenericValue::Option(Some(Box::new(b.clone()))))
+        }
+        (GenericValue::Option(Some(a)), b) => Ok(GenericValue::Option(Some(Box::new(join(a, b)?)))),
+        (a, GenericValue::Option(Some(b))) => Ok(GenericValue::Option(Some(Box::new(join(a, b)?)))),
+        (GenericValue::Array(a), GenericValue::Array(b)) => {
+            // Arrays of different lengths can only be slices, whose length is
+            // not part of the type. Validation rejects them when fixed-size
+            // arrays are enabled, as `uniform_lengths` is false for them.
+            let length = if a.len() == b.len() { a.len() } else { 1 };
+            let element = join_all(a.iter().chain(b))?;
+            Ok(GenericValue::Array(match element {
+                Some(element) => vec![element; length],
+                None => vec![],
+            }))
+        }
+        (GenericValue::Struct(a), GenericValue::Struct(b)) => {
+            let mut fields = LinearMap::new();
+            for (key, a_value) in &a.fields {
+                let value = match b.fields.get(key) {
+                    Some(b_value) => join(a_value, b_value)?,
+                    None => optional(a_value),
+                };
+                fields.insert(key.clone(), value);
+            }
+            for (key, b_value) in &b.fields {
+                if !a.fields.contains_key(key) {
+                    fields.insert(key.clone(), optional(b_value));
+                }
+            }
+            let mut field_docs = b.field_docs.clone();
+            field_docs.extend(a.field_docs.clone());
+            Ok(GenericValue::Struct(GenericStruct {
+                struct_name: a.struct_name.clone(),
+                fields,
+                doc: a.doc.clone().or_else(|| b.doc.clone()),
+                field_docs,
+            }))
+        }
+        (a, b) if is_number(a) && is_number(b) => join_numbers(a, b),
+        (a, b) if type_string(a) == type_string(b) => Ok(a.clone()),
+        _ => Err(Incompatible),
+    }
+}
+
+fn is_number(value: &GenericValue) -> bool {
+    int_value(value).is_some() || float_value(value).is_some()
+}
+
+fn int_value(value: &GenericValue) -> Option<i128> {
+    match *value {
+        GenericValue::I8(x) => Some(x.into()),
+        GenericValue::I16(x) => Some(x.into()),
+        GenericValue::I32(x) => Some(x.into()),
+        GenericValue::I64(x) => Some(x.into()),
+        GenericValue::I128(x) => Some(x),
+        GenericValue::U8(x) => Some(x.into()),
+        GenericValue::U16(x) => Some(x.into()),
+        GenericValue::U32(x) => Some(x.into()),
+        GenericValue::U64(x) => Some(x.into()),
+        GenericValue::ISize(x) => i128::try_from(x).ok(),
+        GenericValue::Usize(x) => i128::try_from(x).ok(),
+        _ => None,
+    }
+}
+
+fn float_value(value: &GenericValue) -> Option<f64> {
+    match *value {
+        GenericValue::F32(x) => Some(x.into()),
+        GenericValue::F64(x) => Some(x),
+        _ => None,
+    }
+}
+
+/// Converts an integer to the same integer type as `like`, if it fits.
+fn int_like(value: i128, like: &GenericValue) -> Option<GenericValue> {
+    match *like {
+        GenericValue::I8(_) => i8::try_from(value).ok().map(GenericValue::I8),
+        GenericValue::I16(_) => i16::try_from(value).ok().map(GenericValue::I16),
+        GenericValue::I32(_) => i32::try_from(value).ok().map(GenericValue::I32),
+        GenericValue::I64(_) => i64::try_from(value).ok().map(GenericValue::I64),
+        GenericValue::I128(_) => Some(GenericValue::I128(value)),
+        GenericValue::U8(_) => u8::try_from(value).ok().map(GenericValue::U8),
+        GenericValue::U16(_) => u16::try_from(value).ok().map(GenericValue::U16),
+        GenericValue::U32(_) => u32::try_from(value).ok().map(GenericValue::U32),
+        GenericValue::U64(_) => u64::try_from(value).ok().map(GenericValue::U64),
+        GenericValue::ISize(_) => isize::try_from(value).ok().map(GenericValue::ISize),
+        GenericValue::Usize(_) => usize::try_from(value).ok().map(GenericValue::Usize),
+        _ => None,
+    }
+}
+
+fn is_unsigned(value: &GenericValue) -> bool {
+    matches!(
+        *value,
+        GenericValue::U8(_)
+            | GenericValue::U16(_)
+            | GenericValue::U32(_)
+            | GenericValue::U64(_)
+            | GenericValue::Usize(_)
+    )
+}
+
+/// Joins two numbers. For integers, the result holds the most extreme value
+/// seen so far (the smallest for signed types, the largest for unsigned
+/// types), so that later joins can still check that widening is lossless.
+#[allow(clippy::cast_possible_truncation)]
+fn join_numbers(a: &GenericValue, b: &GenericValue) -> Result<GenericValue, Incompatible> {
+    match (int_value(a), int_value(b)) {
+        (Some(a_int), Some(b_int)) => {
+            let candidates = [
+                a.clone(),
+                b.clone(),
+                GenericValue::I64(0),
+                GenericValue::U64(0),
+                GenericValue::I128(0),
+            ];
+            candidates
+                .iter()
+                .find(|candidate| {
+                    int_like(a_int, candidate).is_some() && int_like(b_int, candidate).is_some()
+                })
+                .and_then(|candidate| {
+                    let extreme = if is_unsigned(candidate) {
+                        a_int.max(b_int)
+                    } else {
+                        a_int.min(b_int)
+                    };
+                    int_like(extreme, candidate)
+                })
+                .ok_or(Incompatible)
+        }
+        (_, Some(_)) => Ok(a.clone()),
+        (Some(_), _) => Ok(b.clone()),
+        (None, None) => match (a, b) {
+            (GenericValue::F32(_), GenericValue::F32(_)) => Ok(a.clone()),
+            _ => Ok(GenericValue::F64(0.0)),
+        },
+    }
+}
+
+fn optional(value: &GenericValue) -> GenericValue {
+    match value {
+        GenericValue::Option(_) => value.clone(),
+        _ => GenericValue::Option(Some(Box::new(value.clone()))),
+    }
+}
+
+/// Converts a value to the type of `like`, or returns `None` if that would
+/// change the type of `like`.
+#[cfg(feature = "json-parsing")]
+pub fn coerce_like(value: GenericValue, like: &GenericValue) -> Option<GenericValue> {
+    let unified = join(like, &value).ok()?;
+    (super::dedup::shape(&unified) == super::dedup::shape(like)).then(|| coerce(value, &unified))
+}
+
+/// Converts a value to the type represented by `unified`, which must have
+/// been produced by joining it with other values.
+fn coerce(value: GenericValue, unified: &GenericValue) -> GenericValue {
+    match (value, unified) {
+        (GenericValue::Option(Some(value)), GenericValue::Option(Some(unified))) => {
+            GenericValue::Option(Some(Box::new(coerce(*value, unified))))
+        }
+        (value @ GenericValue::Option(_), _) => value,
+        (value, GenericValue::Option(Some(unified))) => {
+            GenericValue::Option(Some(Box::new(coerce(value, unified))))
+        }
+        (GenericValue::Struct(mut struct_value), GenericValue::Struct(unified)) => {
+            struct_value.fields = unified
+                .fields
+                .iter()
+                .map(|(key, unified)| {
+                    let value = match struct_value.fields.remove(key) {
+                        Some(value) => coerce(value, unified),
+                        None => GenericValue::Option(None),
+                    };
+                    (key.clone(), value)
+                })
+                .collect();
+            GenericValue::Struct(struct_value)
+        }
+        (GenericValue::Array(values), GenericValue::Array(unified)) => match unified.first() {
+            Some(unified) => GenericValue::Array(
+                values
+                    .into_iter()
+                    .map(|value| coerce(value, unified))
+                    .collect(),
+            ),
+            None => GenericValue::Array(values),
+        },
+        (value, unified) if is_number(&value) => coerce_number(value, unified),
+        (value, _) => value,
+    }
+}
+
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn coerce_number(value: GenericValue, unified: &GenericValue) -> GenericValue {
+    let float = int_value(&value)
+        .map(|x| x as f64)
+        .or_else(|| float_value(&value));
+
+    match (unified, float) {
+        (GenericValue::F32(_), Some(x)) => GenericValue::F32(x as f32),
+        (GenericValue::F64(_), Some(x)) => GenericValue::F64(x),
+        _ => int_value(&value)
+            .and_then(|x| int_like(x, unified))
+            .unwrap_or(value),
     }
 }
 
@@ -44,3 +454,230 @@ pub fn preferred_int(value: i64, preferred: IntSize) -> GenericValue {
         IntSize::ISize => GenericValue::ISize(value as isize),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(fields: Vec<(&str, GenericValue)>) -> GenericValue {
+        GenericValue::Struct(GenericStruct {
+            struct_name: "Table".to_owned(),
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+            ..GenericStruct::default()
+        })
+    }
+
+    fn field<'a>(value: &'a GenericValue, key: &str) -> &'a GenericValue {
+        match value {
+            GenericValue::Struct(struct_value) => &struct_value.fields[key],
+            _ => panic!("Expected a struct"),
+        }
+    }
+
+    fn keys(value: &GenericValue) -> Vec<&str> {
+        match value {
+            GenericValue::Option(Some(value)) => keys(value),
+            GenericValue::Struct(struct_value) => {
+                struct_value.fields.keys().map(String::as_str).collect()
+            }
+            _ => panic!("Expected a struct"),
+        }
+    }
+
+    #[test]
+    fn optional_values_keep_their_order() {
+        let yx = table(vec![
+            ("y", GenericValue::I32(1)),
+            ("x", GenericValue::I32(2)),
+        ]);
+        let xy = table(vec![
+            ("x", GenericValue::I32(3)),
+            ("y", GenericValue::I32(4)),
+        ]);
+        let null = GenericValue::Option(None);
+
+        // The fields of the first struct come first, whichever side of the
+        // join the `Option` is on.
+        for values in [
+            vec![yx.clone(), null.clone(), xy.clone()],
+            vec![null.clone(), yx.clone(), xy.clone()],
+        ] {
+            let unified = unified_type("", &values).unwrap().unwrap();
+            assert_eq!(keys(&unified), ["y", "x"]);
+        }
+
+        let optional = |value: &GenericValue| GenericValue::Option(Some(Box::new(value.clone())));
+        assert_eq!(keys(&join(&yx, &optional(&xy)).unwrap()), ["y", "x"]);
+        assert_eq!(keys(&join(&optional(&yx), &xy).unwrap()), ["y", "x"]);
+
+        // The first number type which can hold both numbers wins.
+        let joined = join(&GenericValue::I32(1), &optional(&GenericValue::I64(2))).unwrap();
+        assert_eq!(type_string(&joined), "Option<i32>");
+        let joined = join(&optional(&GenericValue::I64(1)), &GenericValue::I32(2)).unwrap();
+        assert_eq!(type_string(&joined), "Option<i64>");
+    }
+
+    #[test]
+    fn merge_layers() {
+        let base = table(vec![
+            ("name", GenericValue::String("base".to_owned())),
+            ("port", GenericValue::I64(80)),
+            (
+                "db",
+                table(vec![("host", GenericValue::String("h".to_owned()))]),
+            ),
+        ]);
+        let layer = table(vec![
+            ("port", GenericValue::String("eighty".to_owned())),
+            ("db", table(vec![("pool", GenericValue::I64(4))])),
+            ("debug", GenericValue::Bool(true)),
+        ]);
+
+        let (mut base, layer) = match (base, layer) {
+            (GenericValue::Struct(base), GenericValue::Struct(layer)) => (base, layer),
+            _ => unreachable!(),
+        };
+        merge_structs(&mut base, layer);
+        let merged = GenericValue::Struct(base);
+
+        assert_eq!(type_string(field(&merged, "name")), "Cow<'static, str>");
+        assert_eq!(type_string(field(&merged, "port")), "Cow<'static, str>");
+        assert_eq!(type_string(field(&merged, "debug")), "bool");
+        let db = field(&merged, "db");
+        assert_eq!(type_string(field(db, "host")), "Cow<'static, str>");
+        assert_eq!(type_string(field(db, "pool")), "i64");
+    }
+
+    #[test]
+    fn unify_missing_fields() {
+        let mut values = vec![
+            table(vec![
+                ("a", GenericValue::I64(1)),
+                ("b", GenericValue::Bool(true)),
+            ]),
+            table(vec![("a", GenericValue::I64(2))]),
+        ];
+        unify_values("tables", &mut values).unwrap();
+
+        assert_eq!(type_string(field(&values[0], "a")), "i64");
+        assert_eq!(type_string(field(&values[0], "b")), "Option<bool>");
+        assert!(matches!(field(&values[1], "b"), GenericValue::Option(None)));
+
+        let unified = unified_type("tables", &values).unwrap().unwrap();
+        assert_eq!(type_string(field(&unified, "b")), "Option<bool>");
+    }
+
+    #[test]
+    fn unify_missing_field_first() {
+        let mut values = vec![
+            table(vec![]),
+            table(vec![("a", GenericValue::String("x".to_owned()))]),
+        ];
+        unify_values("tables", &mut values).unwrap();
+
+        assert!(matches!(field(&values[0], "a"), GenericValue::Option(None)));
+        let unified = unified_type("tables", &values).unwrap().unwrap();
+        assert_eq!(
+            type_string(field(&unified, "a")),
+            "Option<Cow<'static, str>>"
+        );
+    }
+
+    #[test]
+    fn unify_nested_arrays_of_tables() {
+        let mut values = vec![
+            table(vec![("inner", GenericValue::Array(vec![table(vec![])]))]),
+            table(vec![(
+                "inner",
+                GenericValue::Array(vec![table(vec![("x", GenericValue::I64(0))])]),
+            )]),
+        ];
+        unify_values("tables", &mut values).unwrap();
+
+        match field(&values[0], "inner") {
+            GenericValue::Array(inner) => {
+                assert!(matches!(field(&inner[0], "x"), GenericValue::Option(None)));
+            }
+            _ => panic!("Expected an array"),
+        }
+    }
+
+    #[test]
+    fn widen_ints_to_floats() {
+        let mut values = vec![GenericValue::I64(1), GenericValue::F64(2.5)];
+        unify_values("numbers", &mut values).unwrap();
+
+        assert!(matches!(values[0], GenericValue::F64(x) if x == 1.0));
+        assert!(matches!(values[1], GenericValue::F64(x) if x == 2.5));
+    }
+
+    #[test]
+    fn widen_i64_to_u64() {
+        let mut values = vec![GenericValue::I64(1), GenericValue::U64(u64::MAX)];
+        unify_values("numbers", &mut values).unwrap();
+
+        assert!(matches!(values[0], GenericValue::U64(1)));
+        assert!(matches!(values[1], GenericValue::U64(u64::MAX)));
+    }
+
+    #[test]
+    fn widen_i64_to_i128() {
+        let mut values = vec![
+            GenericValue::U64(u64::MAX),
+            GenericValue::I64(1),
+            GenericValue::I64(-1),
+        ];
+        unify_values("numbers", &mut values).unwrap();
+
+        assert!(matches!(values[0], GenericValue::I128(x) if x == u64::MAX.into()));
+        assert!(matches!(values[1], GenericValue::I128(1)));
+        assert!(matches!(values[2], GenericValue::I128(-1)));
+    }
+
+    #[test]
+    fn narrow_ints_stay_narrow() {
+        let mut values = vec![GenericValue::I32(-1), GenericValue::U64(5)];
+        unify_values("numbers", &mut values).unwrap();
+
+        assert!(matches!(values[0], GenericValue::I32(-1)));
+        assert!(matches!(values[1], GenericValue::I32(5)));
+    }
+
+    #[test]
+    fn null_makes_optional() {
+        let mut values = vec![
+            GenericValue::I64(1),
+            GenericValue::Option(None),
+            GenericValue::F64(0.5),
+        ];
+        unify_values("numbers", &mut values).unwrap();
+
+        let unified = unified_type("numbers", &values).unwrap().unwrap();
+        assert_eq!(type_string(&unified), "Option<f64>");
+        assert!(matches!(values[0], GenericValue::Option(Some(_))));
+        assert!(matches!(values[1], GenericValue::Option(None)));
+    }
+
+    #[test]
+    fn widen_fields_of_tables() {
+        let mut values = vec![
+            table(vec![("a", GenericValue::I64(1))]),
+            table(vec![("a", GenericValue::F64(1.5))]),
+        ];
+        unify_values("tables", &mut values).unwrap();
+
+        assert!(matches!(field(&values[0], "a"), GenericValue::F64(_)));
+    }
+
+    #[test]
+    fn unify_mismatched_fields() {
+        let mut values = vec![
+            table(vec![("a", GenericValue::I64(1))]),
+            table(vec![("a", GenericValue::Bool(false))]),
+        ];
+        assert!(unify_values("tables", &mut values).is_err());
+    }
+}
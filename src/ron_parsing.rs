@@ -11,7 +11,8 @@
 use ron::{self, value::Value};
 
 use super::{
-    cperror::GenerationError,
+    cperror::{DeserializationError, GenerationError},
+    key_order::KeyOrder,
     options::StructOptions,
     parsing,
     value::{GenericStruct, GenericValue},
@@ -21,100 +22,149 @@ pub fn parse_ron(ron: &str, options: &StructOptions) -> Result<GenericStruct, Ge
     use parsing::ParsedFields;
 
     let ron_struct = {
-        let ron_object: Value = ron::de::from_str(ron)
-            .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+        let ron_object: Value = ron::de::from_str(ron).map_err(|err| ron_error(err, ron))?;
 
         if let Value::Map(mapping) = ron_object {
             mapping
                 .into_iter()
-                .map(|(key, value)| {
-                    let key = {
-                        if let Value::String(key) = key {
-                            key
-                        } else {
-                            let m = "Top-level keys in RON map must be strings.".to_owned();
-                            return Err(GenerationError::DeserializationFailed(m));
-                        }
-                    };
-                    Ok((key, value))
-                })
+                .map(|(key, value)| Ok((ron_key(key, "")?, value)))
                 .collect::<Result<ParsedFields<Value>, GenerationError>>()?
         } else {
-            let m = "Root RON object must be a struct or map.".to_owned();
-            return Err(GenerationError::DeserializationFailed(m));
+            return Err(GenerationError::UnsupportedValue {
+                key_path: String::new(),
+                kind: format!(
+                    "{} (the root must be a struct or map)",
+                    ron_kind(&ron_object)
+                ),
+            });
         }
     };
 
-    let generic_struct = parsing::parsed_to_generic_struct(ron_struct, options, ron_to_raw_value);
+    let mut generic_struct =
+        parsing::parsed_to_generic_struct(ron_struct, options, ron_to_raw_value)?;
+
+    // Maps are parsed into sorted maps, so the key order is read separately.
+    if options.preserve_key_order {
+        let key_order: KeyOrder = ron::de::from_str(ron).map_err(|err| ron_error(err, ron))?;
+        key_order.apply(&mut generic_struct);
+    }
 
     Ok(generic_struct)
 }
 
-#[allow(clippy::float_cmp)]
+#[allow(
+    clippy::float_cmp,
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation
+)]
 fn ron_to_raw_value(
     super_struct: &str,
     super_key: &str,
+    key_path: &str,
     value: Value,
     options: &StructOptions,
-) -> GenericValue {
-    match value {
+) -> Result<GenericValue, GenerationError> {
+    let value = match value {
         Value::Unit => GenericValue::Unit,
         Value::Bool(value) => GenericValue::Bool(value),
         Value::Char(value) => GenericValue::Char(value),
         Value::Number(value) => {
             let float = value.get();
 
-            if float.trunc() == float {
+            // RON stores every number as a float, so only whole numbers
+            // which fit in an `i64` can be integers.
+            if float.trunc() == float && float >= i64::MIN as f64 && float < i64::MAX as f64 {
                 parsing::preferred_int(float as i64, options.default_int_size)
             } else {
                 parsing::preferred_float(float, options.default_float_size)
             }
         }
         Value::String(value) => GenericValue::String(value),
-        Value::Option(option) => GenericValue::Option(
-            option
-                .map(|value| Box::new(ron_to_raw_value(super_struct, super_key, *value, options))),
-        ),
+        Value::Option(option) => GenericValue::Option(match option {
+            Some(value) => Some(Box::new(ron_to_raw_value(
+                super_struct,
+                super_key,
+                key_path,
+                *value,
+                options,
+            )?)),
+            None => None,
+        }),
         Value::Seq(values) => GenericValue::Array(
             values
                 .into_iter()
-                .map(|value| ron_to_raw_value(super_struct, super_key, value, options))
-                .collect(),
+                .enumerate()
+                .map(|(index, value)| {
+                    let element_path = parsing::element_path(key_path, index);
+                    ron_to_raw_value(super_struct, super_key, &element_path, value, options)
+                })
+                .collect::<Result<_, _>>()?,
         ),
         Value::Map(values) => {
             let sub_struct_name = format!("{super_struct}__{super_key}");
             let values = values
                 .into_iter()
                 .map(|(key, value)| {
-                    let key = {
-                        if let Value::String(key) = key {
-                            key
-                        } else {
-                            unimplemented!("We should handle an error here");
-                        }
-                    };
-                    let value = ron_to_raw_value(&sub_struct_name, &key, value, options);
-                    (key, value)
+                    let key = ron_key(key, key_path)?;
+                    let field_path = parsing::field_path(key_path, &key);
+                    let value =
+                        ron_to_raw_value(&sub_struct_name, &key, &field_path, value, options)?;
+                    Ok((key, value))
                 })
-                .collect();
+                .collect::<Result<_, GenerationError>>()?;
             GenericValue::Struct(GenericStruct {
                 struct_name: sub_struct_name,
                 fields: values,
+                ..GenericStruct::default()
             })
         }
+    };
+
+    Ok(value)
+}
+
+/// Checks that a key of the map at `key_path` is a string.
+fn ron_key(key: Value, key_path: &str) -> Result<String, GenerationError> {
+    match key {
+        Value::String(key) => Ok(key),
+        key => Err(GenerationError::NonStringKey {
+            key_path: key_path.to_owned(),
+            key: ron::ser::to_string(&key).unwrap_or_else(|_| ron_kind(&key).to_owned()),
+        }),
+    }
+}
+
+fn ron_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "a bool",
+        Value::Char(_) => "a char",
+        Value::Map(_) => "a map",
+        Value::Number(_) => "a number",
+        Value::Option(_) => "an option",
+        Value::String(_) => "a string",
+        Value::Seq(_) => "a sequence",
+        Value::Unit => "a unit",
     }
 }
 
-#[allow(dead_code)]
 pub fn parse_map_keys(ron: &str) -> Result<Vec<String>, GenerationError> {
     use linear_map::LinearMap;
 
-    let map: LinearMap<String, Value> = ron::de::from_str(ron)
-        .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+    let map: LinearMap<String, Value> =
+        ron::de::from_str(ron).map_err(|err| ron_error(err, ron))?;
 
     Ok(map.into_iter().map(|pair| pair.0).collect())
 }
 
+/// Converts a RON parse error, pointing at its location in the config.
+pub fn ron_error(error: ron::de::Error, ron: &str) -> GenerationError {
+    let line_column = match &error {
+        ron::de::Error::Parser(_, position) => Some((position.line, position.col)),
+        _ => None,
+    };
+    DeserializationError::from_parser(error, ron, line_column).into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,6 +178,34 @@ mod tests {
     #[test]
     fn test_non_struct_root_object() {
         let ron_code = r#"["key", "value"]"#;
-        parse_ron(ron_code, &StructOptions::default()).unwrap_err();
+        let error = parse_ron(ron_code, &StructOptions::default()).unwrap_err();
+        assert!(matches!(
+            error,
+            GenerationError::UnsupportedValue { ref key_path, .. } if key_path.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_nested_non_string_keys() {
+        let ron_code = r#"(servers: [(ports: {"http": 80}), (ports: {443: "https"})])"#;
+        let error = parse_ron(ron_code, &StructOptions::default()).unwrap_err();
+        match error {
+            GenerationError::NonStringKey { key_path, key } => {
+                assert_eq!(key_path, "servers[1].ports");
+                assert_eq!(key, "443");
+            }
+            error => panic!("expected a non-string key error, got {error:?}"),
+        }
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        let config = parse_ron(
+            "(big: 100000000000000000000.0, small: -5)",
+            &StructOptions::default(),
+        )
+        .unwrap();
+        assert!(matches!(config.fields["big"], GenericValue::F64(x) if x == 1e20));
+        assert!(matches!(config.fields["small"], GenericValue::I64(-5)));
     }
 }
@@ -1,24 +1,40 @@
-use std::path::Path;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::quote;
 
 use super::{
     cperror::{Error, GenerationError},
-    files,
+    dedup, files,
     format::Format,
-    generation, json_parsing, load_fns,
+    generation, load_fns, naming,
     options::{DynamicLoading, StructOptions},
-    ron_parsing, toml_parsing, validation,
+    parsing, validation,
     value::GenericStruct,
-    yaml_parsing,
 };
 
+#[cfg(feature = "json-parsing")]
+use super::{flighting, json_parsing};
+
+#[cfg(feature = "ron-parsing")]
+use super::ron_parsing;
+
+#[cfg(feature = "toml-parsing")]
+use super::toml_parsing;
+
+#[cfg(feature = "yaml-parsing")]
+use super::yaml_parsing;
+
 /// Generate Rust source code defining structs based on a config file.
 ///
 /// The format of the config file will be auto-detected from its extension.
 ///
 /// # Examples
 /// ```rust,no_run
-/// # fn main() -> Result<(), config_struct::Error> {
-/// let code = config_struct::generate_struct("config.toml", &Default::default())?;
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// let code = config_to_struct::generate_struct("config.toml", &Default::default())?;
 /// assert!(code.contains("pub struct Config"));
 /// # Ok(())
 /// # }
@@ -39,10 +55,11 @@ pub fn generate_struct<P: AsRef<Path>>(
 ///
 /// # Examples
 /// ```rust
-/// # fn main() -> Result<(), config_struct::Error> {
-/// use config_struct::{StructOptions, Format};
+/// # #[cfg(feature = "toml-parsing")]
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::{StructOptions, Format};
 ///
-/// let code = config_struct::generate_struct_from_source(
+/// let code = config_to_struct::generate_struct_from_source(
 ///     "number = 100  # This is valid TOML.",
 ///     &StructOptions {
 ///         format: Some(Format::Toml),
@@ -54,6 +71,8 @@ pub fn generate_struct<P: AsRef<Path>>(
 /// assert!(code.contains("number: 100"));
 /// # Ok(())
 /// # }
+/// # #[cfg(not(feature = "toml-parsing"))]
+/// # fn main() {}
 /// ```
 pub fn generate_struct_from_source<S: AsRef<str>>(
     source: S,
@@ -62,52 +81,275 @@ pub fn generate_struct_from_source<S: AsRef<str>>(
     generate_struct_from_source_with_filepath(source.as_ref(), options, None)
 }
 
-#[allow(clippy::items_after_statements, clippy::format_push_string)]
+/// Generate Rust source code defining structs based on a list of
+/// config files, which are merged in order.
+///
+/// Each layer overrides the previous layers at the key level: nested
+/// tables are merged key by key, and any other value replaces the
+/// value under the same key. The types of the structs are inferred
+/// from the merged config, and the generated dynamic load functions
+/// merge the files in the same way at runtime.
+///
+/// All of the files must be in the same format, which will be
+/// auto-detected from their extensions.
+///
+/// # Examples
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// let code = config_to_struct::generate_struct_from_layers(
+///     &["config.toml", "config.dev.toml"],
+///     &Default::default())?;
+/// assert!(code.contains("pub struct Config"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn generate_struct_from_layers<P: AsRef<Path>>(
+    filepaths: &[P],
+    options: &StructOptions,
+) -> Result<String, Error> {
+    let sources = read_layers(filepaths)?;
+    let layers = sources
+        .iter()
+        .map(|(source, path)| (source.as_str(), Some(*path)))
+        .collect::<Vec<Layer>>();
+    let output = generate_struct_from_layer_sources(&layers, options)?;
+
+    Ok(output)
+}
+
+/// Generate a token stream defining structs based on a config file.
+///
+/// This produces the same items as [`generate_struct`](fn.generate_struct.html),
+/// without the file-level attributes, so that they can be spliced into the
+/// output of a procedural macro or another code generator.
+///
+/// # Examples
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// let tokens = config_to_struct::generate_struct_tokens("config.toml", &Default::default())?;
+/// assert!(tokens.to_string().contains("pub struct Config"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn generate_struct_tokens<P: AsRef<Path>>(
+    filepath: P,
+    options: &StructOptions,
+) -> Result<TokenStream, Error> {
+    let path = filepath.as_ref();
+    let source = std::fs::read_to_string(path)?;
+    let tokens = generate_struct_tokens_with_filepath(&source, options, Some(path))?;
+
+    Ok(tokens)
+}
+
+/// Generate a token stream defining structs from a config string
+/// in a format specified in the provided options.
+///
+/// # Examples
+/// ```rust
+/// # #[cfg(feature = "toml-parsing")]
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::{StructOptions, Format};
+///
+/// let tokens = config_to_struct::generate_struct_tokens_from_source(
+///     "number = 100  # This is valid TOML.",
+///     &StructOptions {
+///         format: Some(Format::Toml),
+///         ..Default::default()
+///     })?;
+///
+/// assert!(tokens.to_string().contains("pub struct Config"));
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "toml-parsing"))]
+/// # fn main() {}
+/// ```
+pub fn generate_struct_tokens_from_source<S: AsRef<str>>(
+    source: S,
+    options: &StructOptions,
+) -> Result<TokenStream, GenerationError> {
+    generate_struct_tokens_with_filepath(source.as_ref(), options, None)
+}
+
+/// Generate a token stream defining structs based on a list of config
+/// files, which are merged in order.
+///
+/// This produces the same items as
+/// [`generate_struct_from_layers`](fn.generate_struct_from_layers.html),
+/// without the file-level attributes.
+pub fn generate_struct_tokens_from_layers<P: AsRef<Path>>(
+    filepaths: &[P],
+    options: &StructOptions,
+) -> Result<TokenStream, Error> {
+    let sources = read_layers(filepaths)?;
+    let layers = sources
+        .iter()
+        .map(|(source, path)| (source.as_str(), Some(*path)))
+        .collect::<Vec<Layer>>();
+    let tokens = generate_struct_tokens_from_layer_sources(&layers, options)?;
+
+    Ok(tokens)
+}
+
 fn generate_struct_from_source_with_filepath(
     source: &str,
     options: &StructOptions,
     filepath: Option<&Path>,
 ) -> Result<String, GenerationError> {
+    generate_struct_from_layer_sources(&[(source, filepath)], options)
+}
+
+fn generate_struct_tokens_with_filepath(
+    source: &str,
+    options: &StructOptions,
+    filepath: Option<&Path>,
+) -> Result<TokenStream, GenerationError> {
+    generate_struct_tokens_from_layer_sources(&[(source, filepath)], options)
+}
+
+/// A config source, and the file it was read from if there is one.
+type Layer<'a> = (&'a str, Option<&'a Path>);
+
+fn generate_struct_from_layer_sources(
+    layers: &[Layer],
+    options: &StructOptions,
+) -> Result<String, GenerationError> {
+    let (config, format) = parse_struct(layers, options)?;
+
+    // The comment at the top of the config documents the whole module.
+    let mut tokens = generation::generate_file_doc(&config);
+    tokens.extend(struct_tokens(&config, format, options, layers)?);
+
+    Ok(generation::render_file(tokens))
+}
+
+fn generate_struct_tokens_from_layer_sources(
+    layers: &[Layer],
+    options: &StructOptions,
+) -> Result<TokenStream, GenerationError> {
+    let (config, format) = parse_struct(layers, options)?;
+
+    struct_tokens(&config, format, options, layers)
+}
+
+/// Reads the layers from the given files, in order.
+fn read_layers<P: AsRef<Path>>(filepaths: &[P]) -> Result<Vec<(String, &Path)>, Error> {
+    filepaths
+        .iter()
+        .map(|filepath| {
+            let path = filepath.as_ref();
+            Ok((std::fs::read_to_string(path)?, path))
+        })
+        .collect()
+}
+
+fn parse_struct(
+    layers: &[Layer],
+    options: &StructOptions,
+) -> Result<(GenericStruct, Format), GenerationError> {
     options.validate()?;
 
-    let format = match options.format {
-        Some(format) => format,
-        None => match filepath {
-            Some(path) => Format::from_filename(path)?,
-            None => return Err(GenerationError::UnknownInputFormat("<none>".into())),
-        },
+    let format = match (options.format, layers.first()) {
+        (Some(format), _) => format,
+        (None, Some((_, Some(path)))) => Format::from_filename(path)?,
+        (None, Some((_, None))) => {
+            return Err(GenerationError::UnknownInputFormat("<none>".into()))
+        }
+        (None, None) => return Err(GenerationError::MissingLayers),
     };
 
-    let config = {
-        let mut root_struct: GenericStruct = match format {
-            #[cfg(feature = "json-parsing")]
-            Format::Json => json_parsing::parse_json(source, options)?,
+    let mut config: Option<GenericStruct> = None;
+    for &(source, filepath) in layers {
+        #[cfg(feature = "json-parsing")]
+        if let Some(path) = filepath.filter(|path| flighting::is_flighting_file(path)) {
+            let config = config.as_mut().ok_or_else(|| {
+                GenerationError::InvalidFlighting(format!(
+                    "`{}` must follow a config layer to patch",
+                    path.display()
+                ))
+            })?;
+            flighting::parse_flighting(source, options)
+                .and_then(|flighting| flighting.apply(config))
+                .map_err(|error| error.in_file(filepath))?;
+            continue;
+        }
 
-            #[cfg(feature = "ron-parsing")]
-            Format::Ron => ron_parsing::parse_ron(source, options)?,
+        if let (None, Some(path)) = (options.format, filepath) {
+            if Format::from_filename(path)? != format {
+                return Err(GenerationError::MismatchedLayerFormat(
+                    path.display().to_string(),
+                ));
+            }
+        }
 
-            #[cfg(feature = "toml-parsing")]
-            Format::Toml => toml_parsing::parse_toml(source, options)?,
+        let layer =
+            parse_layer(source, format, options).map_err(|error| error.in_file(filepath))?;
+        match &mut config {
+            Some(config) => parsing::merge_structs(config, layer),
+            None => config = Some(layer),
+        }
+    }
+    let mut config = config.ok_or(GenerationError::MissingLayers)?;
+    config.struct_name.clone_from(&options.struct_name);
 
-            #[cfg(feature = "yaml-parsing")]
-            Format::Yaml => yaml_parsing::parse_yaml(source, options)?,
-        };
-        root_struct.struct_name.clone_from(&options.struct_name);
-        root_struct
-    };
+    if !options.preserve_key_order {
+        parsing::sort_fields(&mut config);
+    }
+
+    if options.report_all_errors {
+        validation::validate_struct_fully(&config, options)?;
+    } else {
+        validation::validate_struct(&config, options)?;
+    }
+    parsing::unify_struct(&mut config)?;
+    naming::name_structs(&mut config, options)?;
+
+    if options.deduplicate_structs {
+        dedup::deduplicate_structs(&mut config, options);
+    }
+
+    Ok((config, format))
+}
 
-    validation::validate_struct(&config)?;
+fn parse_layer(
+    source: &str,
+    format: Format,
+    options: &StructOptions,
+) -> Result<GenericStruct, GenerationError> {
+    // Comments are carried over from TOML and YAML, but not from the other
+    // formats.
+    let layer = match format {
+        #[cfg(feature = "json-parsing")]
+        Format::Json => json_parsing::parse_json(source, options)?,
 
-    let mut code = String::new();
+        #[cfg(feature = "ron-parsing")]
+        Format::Ron => ron_parsing::parse_ron(source, options)?,
 
-    const HEADER: &str = "#![cfg_attr(rustfmt, rustfmt_skip)]
-#![allow(dead_code)]
+        #[cfg(feature = "toml-parsing")]
+        Format::Toml => {
+            let mut layer = toml_parsing::parse_toml(source, options)?;
+            super::comments::toml_comments(source).apply(&mut layer);
+            layer
+        }
 
-use std::borrow::Cow;\n\n";
-    code.push_str(HEADER);
+        #[cfg(feature = "yaml-parsing")]
+        Format::Yaml => {
+            let mut layer = yaml_parsing::parse_yaml(source, options)?;
+            super::comments::yaml_comments(source).apply(&mut layer);
+            layer
+        }
+    };
+
+    Ok(layer)
+}
 
-    let structs = generation::generate_structs(&config, options);
-    code.push_str(&structs);
+fn struct_tokens(
+    config: &GenericStruct,
+    format: Format,
+    options: &StructOptions,
+    layers: &[Layer],
+) -> Result<TokenStream, GenerationError> {
+    let mut tokens = generation::generate_structs(config, options);
 
     let requires_const =
         options.generate_load_fns && options.dynamic_loading != DynamicLoading::Always;
@@ -116,41 +358,61 @@ use std::borrow::Cow;\n\n";
     let const_name = &options.real_const_name();
 
     if options.generate_const || requires_const {
-        code.push_str(&format!(
-            "pub const {}: {} = {};\n",
-            const_name,
-            struct_name,
-            generation::struct_value_string(&config, 0, options.max_array_size)
-        ));
+        tokens.extend(generation::generate_const(config, options));
     }
 
     if options.generate_load_fns {
-        let filepath = filepath.ok_or(GenerationError::MissingFilePath);
+        let filepaths = layers
+            .iter()
+            .map(|(_, filepath)| filepath.ok_or(GenerationError::MissingFilePath))
+            .collect::<Result<Vec<&Path>, GenerationError>>();
+
+        // Flighting is only applied at generation time, so loading the
+        // layers at runtime would give a different config.
+        #[cfg(feature = "json-parsing")]
+        let filepaths = filepaths.and_then(|filepaths| {
+            if filepaths
+                .iter()
+                .any(|path| flighting::is_flighting_file(path))
+            {
+                Err(GenerationError::DynamicFlighting)
+            } else {
+                Ok(filepaths)
+            }
+        });
 
-        let dynamic_impl =
-            filepath.map(|path| load_fns::dynamic_load_impl(format, struct_name, path));
+        let env_prefix = options.env_prefix.as_deref();
+        let layered = layers.len() > 1;
 
-        let static_impl = load_fns::static_load_impl(struct_name, const_name);
+        let dynamic_impl = filepaths.map(|filepaths| {
+            load_fns::dynamic_load_impl(format, struct_name, &filepaths, env_prefix)
+        });
 
-        let impl_string = match options.dynamic_loading {
+        let static_impl = load_fns::static_load_impl(struct_name, const_name, env_prefix, layered);
+
+        let impl_tokens = match options.dynamic_loading {
             DynamicLoading::Always => dynamic_impl?,
             DynamicLoading::Never => static_impl,
-            DynamicLoading::DebugOnly => format!(
-                "
-#[cfg(debug_assertions)]
-{}
+            DynamicLoading::DebugOnly => {
+                let dynamic_impl = dynamic_impl?;
+                quote! {
+                    #[cfg(debug_assertions)]
+                    #dynamic_impl
 
-#[cfg(not(debug_assertions))]
-{}
-",
-                dynamic_impl?, static_impl,
-            ),
+                    #[cfg(not(debug_assertions))]
+                    #static_impl
+                }
+            }
         };
 
-        code.push_str(&impl_string);
+        tokens.extend(impl_tokens);
+
+        if env_prefix.is_some() {
+            tokens.extend(load_fns::env_overrides_impl(config, options));
+        }
     }
 
-    Ok(code)
+    Ok(tokens)
 }
 
 /// Generate a Rust module containing struct definitions based on a
@@ -162,10 +424,10 @@ use std::borrow::Cow;\n\n";
 /// # Examples
 ///
 /// ```rust,no_run
-/// # fn main() -> Result<(), config_struct::Error> {
-/// use config_struct::StructOptions;
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::StructOptions;
 ///
-/// config_struct::create_struct("config.toml", "src/config.rs", &StructOptions::default())?;
+/// config_to_struct::create_struct("config.toml", "src/config.rs", &StructOptions::default())?;
 /// # Ok(())
 /// # }
 /// ```
@@ -174,23 +436,251 @@ pub fn create_struct<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
     destination: DstPath,
     options: &StructOptions,
 ) -> Result<(), Error> {
-    let output = generate_struct(filepath, options)?;
-    files::ensure_destination(destination.as_ref(), options.create_dirs)?;
-    files::write_destination(destination.as_ref(), output, options.write_only_if_changed)?;
+    create_struct_with_directives(filepath, destination, options, &mut std::io::stdout())
+}
+
+/// The same as [`create_struct`](fn.create_struct.html), except that any
+/// `cargo:rerun-if-changed` directives are written to `directives`
+/// instead of being printed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::StructOptions;
+///
+/// let mut directives = Vec::new();
+/// config_to_struct::create_struct_with_directives(
+///     "config.toml",
+///     "src/config.rs",
+///     &StructOptions {
+///         emit_rerun_if_changed: true,
+///         ..Default::default()
+///     },
+///     &mut directives)?;
+///
+/// assert_eq!(directives, b"cargo:rerun-if-changed=config.toml\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_struct_with_directives<SrcPath, DstPath, W>(
+    filepath: SrcPath,
+    destination: DstPath,
+    options: &StructOptions,
+    directives: &mut W,
+) -> Result<(), Error>
+where
+    SrcPath: AsRef<Path>,
+    DstPath: AsRef<Path>,
+    W: Write,
+{
+    create_struct_from_layers_with_directives(
+        &[filepath.as_ref()],
+        destination.as_ref(),
+        options,
+        directives,
+    )
+}
+
+/// Generate a Rust module containing struct definitions based on a
+/// list of config files, which are merged in order as described in
+/// [`generate_struct_from_layers`](fn.generate_struct_from_layers.html).
+///
+/// If `emit_rerun_if_changed` is set, a `cargo:rerun-if-changed`
+/// directive is printed for every file.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::StructOptions;
+///
+/// config_to_struct::create_struct_from_layers(
+///     &["config.toml", "config.dev.toml"],
+///     "src/config.rs",
+///     &StructOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_struct_from_layers<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
+    filepaths: &[SrcPath],
+    destination: DstPath,
+    options: &StructOptions,
+) -> Result<(), Error> {
+    let filepaths = filepaths.iter().map(AsRef::as_ref).collect::<Vec<&Path>>();
+    create_struct_from_layers_with_directives(
+        &filepaths,
+        destination.as_ref(),
+        options,
+        &mut std::io::stdout(),
+    )
+}
+
+fn create_struct_from_layers_with_directives<W: Write>(
+    filepaths: &[&Path],
+    destination: &Path,
+    options: &StructOptions,
+    directives: &mut W,
+) -> Result<(), Error> {
+    write_struct(
+        filepaths,
+        destination,
+        options,
+        directives,
+        |filepaths, options| generate_struct_from_layers(filepaths, options),
+    )
+}
+
+/// Writes the code produced by `generate` from the layers to `destination`,
+/// after writing any `cargo:rerun-if-changed` directives to `directives`.
+fn write_struct<W: Write>(
+    filepaths: &[&Path],
+    destination: &Path,
+    options: &StructOptions,
+    directives: &mut W,
+    generate: fn(&[&Path], &StructOptions) -> Result<String, Error>,
+) -> Result<(), Error> {
+    if options.emit_rerun_if_changed {
+        files::write_rerun_directives(directives, filepaths)?;
+    }
+
+    let output = generate(filepaths, options)?;
+    files::ensure_destination(destination, options.create_dirs)?;
+    files::write_destination(destination, output, options.write_only_if_changed)?;
 
     Ok(())
 }
 
+/// The items generated from the layers, rendered for use with `include!`.
+fn generate_struct_items_from_layers(
+    filepaths: &[&Path],
+    options: &StructOptions,
+) -> Result<String, Error> {
+    let tokens = generate_struct_tokens_from_layers(filepaths, options)?;
+
+    Ok(generation::render_items(tokens))
+}
+
+/// Generate a Rust file containing struct definitions based on a
+/// given config file, and write it to the build script's `OUT_DIR`.
+///
+/// The generated file has no inner attributes, so that it can be
+/// included with `include!`. The path that was written to is
+/// returned.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// // build.rs
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::StructOptions;
+///
+/// config_to_struct::create_struct_in_out_dir("config.toml", "config.rs", &StructOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ```rust,ignore
+/// // main.rs
+/// include!(concat!(env!("OUT_DIR"), "/config.rs"));
+/// ```
+pub fn create_struct_in_out_dir<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
+    filepath: SrcPath,
+    filename: DstPath,
+    options: &StructOptions,
+) -> Result<PathBuf, Error> {
+    create_struct_in_out_dir_with_directives(filepath, filename, options, &mut std::io::stdout())
+}
+
+/// The same as [`create_struct_in_out_dir`](fn.create_struct_in_out_dir.html),
+/// except that any `cargo:rerun-if-changed` directives are written to
+/// `directives` instead of being printed.
+pub fn create_struct_in_out_dir_with_directives<SrcPath, DstPath, W>(
+    filepath: SrcPath,
+    filename: DstPath,
+    options: &StructOptions,
+    directives: &mut W,
+) -> Result<PathBuf, Error>
+where
+    SrcPath: AsRef<Path>,
+    DstPath: AsRef<Path>,
+    W: Write,
+{
+    create_struct_from_layers_in_out_dir_with_directives(
+        &[filepath.as_ref()],
+        files::out_dir(),
+        filename.as_ref(),
+        options,
+        directives,
+    )
+}
+
+/// Generate a Rust file containing struct definitions based on a
+/// list of config files, which are merged in order, and write it to
+/// the build script's `OUT_DIR`.
+///
+/// This is the layered version of
+/// [`create_struct_in_out_dir`](fn.create_struct_in_out_dir.html).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// // build.rs
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::StructOptions;
+///
+/// config_to_struct::create_struct_from_layers_in_out_dir(
+///     &["config.toml", "config.dev.toml"],
+///     "config.rs",
+///     &StructOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_struct_from_layers_in_out_dir<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
+    filepaths: &[SrcPath],
+    filename: DstPath,
+    options: &StructOptions,
+) -> Result<PathBuf, Error> {
+    let filepaths = filepaths.iter().map(AsRef::as_ref).collect::<Vec<&Path>>();
+    create_struct_from_layers_in_out_dir_with_directives(
+        &filepaths,
+        files::out_dir(),
+        filename.as_ref(),
+        options,
+        &mut std::io::stdout(),
+    )
+}
+
+fn create_struct_from_layers_in_out_dir_with_directives<W: Write>(
+    filepaths: &[&Path],
+    out_dir: Option<OsString>,
+    filename: &Path,
+    options: &StructOptions,
+    directives: &mut W,
+) -> Result<PathBuf, Error> {
+    let destination = files::out_dir_destination(out_dir, filename)?;
+    write_struct(
+        filepaths,
+        &destination,
+        options,
+        directives,
+        generate_struct_items_from_layers,
+    )?;
+
+    Ok(destination)
+}
+
 /// Generate a Rust module containing struct definitions from a
 /// config string in a format specified by the provided options.
 ///
 /// # Examples
 ///
 /// ```rust,no_run
-/// # fn main() -> Result<(), config_struct::Error> {
-/// use config_struct::{Format, StructOptions};
+/// # #[cfg(feature = "toml-parsing")]
+/// # fn main() -> Result<(), config_to_struct::Error> {
+/// use config_to_struct::{Format, StructOptions};
 ///
-/// config_struct::create_struct_from_source(
+/// config_to_struct::create_struct_from_source(
 ///     "number = 100  # This is valid TOML.",
 ///     "src/config.rs",
 ///     &StructOptions {
@@ -199,6 +689,8 @@ pub fn create_struct<SrcPath: AsRef<Path>, DstPath: AsRef<Path>>(
 ///     })?;
 /// # Ok(())
 /// # }
+/// # #[cfg(not(feature = "toml-parsing"))]
+/// # fn main() {}
 /// ```
 pub fn create_struct_from_source<S: AsRef<str>, P: AsRef<Path>>(
     source: S,
@@ -211,3 +703,626 @@ pub fn create_struct_from_source<S: AsRef<str>, P: AsRef<Path>>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "json-parsing")]
+    use crate::cperror::OptionsError;
+    #[cfg(feature = "json-parsing")]
+    use crate::options::{Edition, SerdeSupport};
+    #[cfg(feature = "toml-parsing")]
+    use crate::temp_dir::TempDir;
+    use crate::value::GenericValue;
+
+    const ESCAPED: &str = r#""quote \" backslash \\ newline \n tab \t unicode é 😇""#;
+
+    fn generate(source: &str, format: Format) -> String {
+        generate_struct_from_source(
+            source,
+            &StructOptions {
+                format: Some(format),
+                ..StructOptions::default()
+            },
+        )
+        .unwrap()
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn tokens_match_generated_code() {
+        let options = StructOptions {
+            format: Some(Format::Toml),
+            ..StructOptions::default()
+        };
+        let tokens = generate_struct_tokens_from_source("name = 'a'", &options).unwrap();
+
+        assert_eq!(
+            generation::render_file(tokens.clone()),
+            generate_struct_from_source("name = 'a'", &options).unwrap()
+        );
+        assert!(!tokens.to_string().contains("#!"));
+        let expected = quote! {
+            pub const CONFIG: Config = Config { name: ::std::borrow::Cow::Borrowed("a"), };
+        };
+        assert!(tokens.to_string().contains(&expected.to_string()));
+    }
+
+    #[cfg(feature = "yaml-parsing")]
+    #[test]
+    fn key_order() {
+        let source = "zeta: 1\nalpha:\n  y: true\n  x: false\n";
+        let generate = |preserve_key_order| {
+            let options = StructOptions {
+                format: Some(Format::Yaml),
+                preserve_key_order,
+                ..StructOptions::default()
+            };
+            generate_struct_tokens_from_source(source, &options)
+                .unwrap()
+                .to_string()
+        };
+
+        let sorted = generate(false);
+        let expected = quote! { pub struct Config { pub alpha: _Config__alpha, pub zeta: i64, } };
+        assert!(sorted.contains(&expected.to_string()));
+        let expected = quote! { _Config__alpha { x: false, y: true, }, zeta: 1, };
+        assert!(sorted.contains(&expected.to_string()));
+
+        let preserved = generate(true);
+        let expected = quote! { pub struct Config { pub zeta: i64, pub alpha: _Config__alpha, } };
+        assert!(preserved.contains(&expected.to_string()));
+        let expected = quote! { pub struct _Config__alpha { pub y: bool, pub x: bool, } };
+        assert!(preserved.contains(&expected.to_string()));
+        let expected = quote! { zeta: 1, alpha: _Config__alpha { y: true, x: false, }, };
+        assert!(preserved.contains(&expected.to_string()));
+    }
+
+    fn keys(struct_value: &GenericStruct) -> Vec<&str> {
+        struct_value.fields.keys().map(String::as_str).collect()
+    }
+
+    fn nested<'a>(struct_value: &'a GenericStruct, key: &str) -> &'a GenericStruct {
+        match &struct_value.fields[key] {
+            GenericValue::Struct(nested) => nested,
+            GenericValue::Array(values) => match &values[0] {
+                GenericValue::Struct(nested) => nested,
+                _ => panic!("expected an array of structs"),
+            },
+            _ => panic!("expected a struct"),
+        }
+    }
+
+    fn check_key_order(source: &str, format: Format) {
+        let options = StructOptions {
+            preserve_key_order: true,
+            ..StructOptions::default()
+        };
+        let mut config = parse_layer(source, format, &options).unwrap();
+        assert_eq!(keys(&config), ["z", "a", "b"]);
+        assert_eq!(keys(nested(&config, "a")), ["y", "x"]);
+        assert_eq!(keys(nested(&config, "b")), ["q", "p"]);
+
+        parsing::sort_fields(&mut config);
+        assert_eq!(keys(&config), ["a", "b", "z"]);
+        assert_eq!(keys(nested(&config, "a")), ["x", "y"]);
+        assert_eq!(keys(nested(&config, "b")), ["p", "q"]);
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn json_key_order() {
+        let source = r#"{ "z": 1, "a": { "y": 1, "x": 2 }, "b": [{ "q": 1, "p": 2 }] }"#;
+        check_key_order(source, Format::Json);
+    }
+
+    #[cfg(feature = "ron-parsing")]
+    #[test]
+    fn ron_key_order() {
+        let source = "(z: 1, a: (y: 1, x: 2), b: [(q: 1, p: 2)])";
+        check_key_order(source, Format::Ron);
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn toml_key_order() {
+        let source = "z = 1\n[a]\ny = 1\nx = 2\n[[b]]\nq = 1\np = 2\n";
+        check_key_order(source, Format::Toml);
+    }
+
+    #[cfg(feature = "yaml-parsing")]
+    #[test]
+    fn yaml_key_order() {
+        let source = "z: 1\na:\n  y: 1\n  x: 2\nb:\n  - q: 1\n    p: 2\n";
+        check_key_order(source, Format::Yaml);
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn comment_docs() {
+        let source = "# Settings for the app.\n\n# The app's name.\nname = 'a'\n\n[server]\n# The port.\nport = 80\n";
+        let code = generate(source, Format::Toml);
+
+        assert!(code.contains("//! Settings for the app.\n"));
+        assert!(code.contains("/// Settings for the app.\n#[derive"));
+        assert!(code.contains("    /// The app's name.\n    pub name:"));
+        assert!(code.contains("    /// The port.\n    pub port: i64,"));
+
+        let options = StructOptions {
+            format: Some(Format::Toml),
+            ..StructOptions::default()
+        };
+        let tokens = generate_struct_tokens_from_source(source, &options).unwrap();
+        assert!(!tokens.to_string().contains("#!"));
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn rerun_directives() {
+        let dir = TempDir::new("rerun");
+        let source = dir.join("config.toml");
+        std::fs::write(&source, "number = 1").unwrap();
+
+        let create = |emit_rerun_if_changed| {
+            let mut directives = Vec::new();
+            let options = StructOptions {
+                emit_rerun_if_changed,
+                ..StructOptions::default()
+            };
+            create_struct_with_directives(
+                &source,
+                dir.join("config.rs"),
+                &options,
+                &mut directives,
+            )
+            .unwrap();
+            String::from_utf8(directives).unwrap()
+        };
+
+        assert_eq!(create(false), "");
+        assert_eq!(
+            create(true),
+            format!("cargo:rerun-if-changed={}\n", source.display())
+        );
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn layers() {
+        let dir = TempDir::new("layers");
+        let base = dir.join("config.toml");
+        let dev = dir.join("config.dev.toml");
+        std::fs::write(&base, "port = 80\n[db]\nhost = 'h'\n").unwrap();
+        std::fs::write(&dev, "port = 81\n[db]\npool = 4\n").unwrap();
+
+        let options = StructOptions {
+            generate_load_fns: true,
+            emit_rerun_if_changed: true,
+            ..StructOptions::default()
+        };
+        let tokens = generate_struct_tokens_from_layers(&[&base, &dev], &options)
+            .unwrap()
+            .to_string();
+        let expected = quote! { pub struct _Config__db { pub host: ::std::borrow::Cow<'static, str>, pub pool: i64, } };
+        assert!(tokens.contains(&expected.to_string()));
+        assert!(tokens.contains(&quote! { port: 81, }.to_string()));
+        assert!(tokens.contains("load_from_layers"));
+
+        let mut directives = Vec::new();
+        create_struct_from_layers_with_directives(
+            &[&base, &dev],
+            &dir.join("config.rs"),
+            &options,
+            &mut directives,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(directives).unwrap(),
+            format!(
+                "cargo:rerun-if-changed={}\ncargo:rerun-if-changed={}\n",
+                base.display(),
+                dev.display()
+            )
+        );
+
+        #[cfg(feature = "json-parsing")]
+        {
+            let json = dir.join("config.json");
+            std::fs::write(&json, "{}").unwrap();
+            assert!(matches!(
+                generate_struct_from_layers(&[&base, &json], &StructOptions::default()),
+                Err(Error::Generation(GenerationError::MismatchedLayerFormat(_)))
+            ));
+        }
+        let no_layers: &[&Path] = &[];
+        assert!(matches!(
+            generate_struct_from_layers(no_layers, &StructOptions::default()),
+            Err(Error::Generation(GenerationError::MissingLayers))
+        ));
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn report_all_errors() {
+        let source = r#"{ "bad key": 1, "mixed": [1, "a"] }"#;
+        let options = StructOptions {
+            format: Some(Format::Json),
+            ..StructOptions::default()
+        };
+        assert!(matches!(
+            generate_struct_from_source(source, &options),
+            Err(GenerationError::InvalidFieldName(_))
+        ));
+
+        let options = StructOptions {
+            report_all_errors: true,
+            ..options
+        };
+        assert!(matches!(
+            generate_struct_from_source(source, &options),
+            Err(GenerationError::InvalidConfig(report)) if report.diagnostics.len() == 2
+        ));
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn sanitize_keys() {
+        let source = r#"{ "max-connections": 8, "type": "a", "Content-Type": "b", "plain": 1 }"#;
+        let options = StructOptions {
+            format: Some(Format::Json),
+            serde_support: SerdeSupport::Yes,
+            ..StructOptions::default()
+        };
+        assert!(matches!(
+            generate_struct_from_source(source, &options),
+            Err(GenerationError::InvalidFieldName(_))
+        ));
+
+        let options = StructOptions {
+            sanitize_keys: true,
+            ..options
+        };
+        let code = generate_struct_from_source(source, &options).unwrap();
+        assert!(
+            code.contains("#[serde(rename = \"max-connections\")]\n    pub max_connections: i64,")
+        );
+        assert!(code.contains("    pub r#type: ::std::borrow::Cow<'static, str>,"));
+        assert!(code.contains("#[serde(rename = \"Content-Type\")]"));
+        assert!(code.contains("    pub plain: i64,"));
+        assert!(!code.contains("rename = \"plain\""));
+        // Serde matches raw identifiers without their prefix.
+        assert!(!code.contains("rename = \"type\""));
+        assert!(code.contains("r#type: ::std::borrow::Cow::Borrowed(\"a\"),"));
+
+        let source = r#"{ "nested": { "max-size": 1, "max_size": 2 } }"#;
+        assert!(matches!(
+            generate_struct_from_source(source, &options),
+            Err(GenerationError::DuplicateFieldName { key_path, first_key, second_key, field_name })
+                if key_path == "nested"
+                    && first_key == "max-size"
+                    && second_key == "max_size"
+                    && field_name == "max_size"
+        ));
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn converted_field_names() {
+        let options = StructOptions {
+            format: Some(Format::Json),
+            serde_support: SerdeSupport::Yes,
+            ..StructOptions::default()
+        };
+        let code =
+            generate_struct_from_source(r#"{ "maxSize": 1, "plain": 2 }"#, &options).unwrap();
+        assert!(code.contains("#[serde(rename = \"maxSize\")]\n    pub max_size: i64,"));
+        assert!(!code.contains("rename = \"plain\""));
+
+        // Without serde there is nothing to rename.
+        let code = generate_struct_from_source(
+            r#"{ "maxSize": 1 }"#,
+            &StructOptions {
+                serde_support: SerdeSupport::No,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert!(!code.contains("serde"));
+
+        let source = r#"{ "table": { "maxSize": 1, "max_size": 2 } }"#;
+        let error = generate_struct_from_source(source, &options).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The keys `maxSize` and `max_size` of the map at `table` would both become the field `max_size`."
+        );
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn keywords() {
+        let options = StructOptions {
+            format: Some(Format::Json),
+            ..StructOptions::default()
+        };
+        let source = r#"{ "type": 1, "match": 2 }"#;
+        assert!(matches!(
+            generate_struct_from_source(source, &options),
+            Err(GenerationError::InvalidFieldName(name)) if name == "match"
+        ));
+
+        let raw = StructOptions {
+            raw_identifiers: true,
+            ..options.clone()
+        };
+        let code = generate_struct_from_source(source, &raw).unwrap();
+        assert!(code.contains("    pub r#match: i64,\n    pub r#type: i64,"));
+        assert!(code.contains("r#type: 1 }"));
+
+        // Keys are checked after converting them to snake_case, and path
+        // keywords cannot be raw identifiers.
+        assert!(matches!(
+            generate_struct_from_source(r#"{ "Self": 1 }"#, &raw),
+            Err(GenerationError::InvalidFieldName(name)) if name == "Self"
+        ));
+
+        // `gen` is only a keyword from Rust 2024.
+        assert!(generate_struct_from_source(r#"{ "gen": 1 }"#, &options).is_err());
+        let rust_2021 = StructOptions {
+            edition: Edition::Rust2021,
+            ..options.clone()
+        };
+        assert!(generate_struct_from_source(r#"{ "gen": 1 }"#, &rust_2021).is_ok());
+
+        let const_name = StructOptions {
+            const_name: Some("static".to_owned()),
+            ..options
+        };
+        assert!(matches!(
+            generate_struct_from_source("{}", &const_name),
+            Err(GenerationError::StructOptions(
+                OptionsError::InvalidConstName(_)
+            ))
+        ));
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn env_prefix_requires_clone() {
+        let options = StructOptions {
+            format: Some(Format::Json),
+            env_prefix: Some("APP".to_owned()),
+            dynamic_loading: DynamicLoading::Never,
+            ..StructOptions::serde_default()
+        };
+        assert!(generate_struct_from_source(r#"{ "port": 80 }"#, &options).is_ok());
+
+        let by_path = StructOptions {
+            derived_traits: vec!["Debug".to_owned(), "std::clone::Clone".to_owned()],
+            ..options.clone()
+        };
+        assert!(generate_struct_from_source(r#"{ "port": 80 }"#, &by_path).is_ok());
+
+        let without_clone = StructOptions {
+            derived_traits: vec!["Debug".to_owned()],
+            ..options
+        };
+        assert!(matches!(
+            generate_struct_from_source(r#"{ "port": 80 }"#, &without_clone),
+            Err(GenerationError::StructOptions(
+                OptionsError::EnvPrefixRequiresClone
+            ))
+        ));
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn mismatched_array_lengths() {
+        let fixed = StructOptions {
+            format: Some(Format::Json),
+            max_array_size: 4,
+            ..StructOptions::default()
+        };
+        let slices = StructOptions {
+            max_array_size: 0,
+            ..fixed.clone()
+        };
+
+        let nested = r#"{ "a": [[1, 2], [3]] }"#;
+        assert!(matches!(
+            generate_struct_from_source(nested, &fixed),
+            Err(GenerationError::MismatchedArrayLengths(key)) if key == "a"
+        ));
+        let code = generate_struct_from_source(nested, &slices).unwrap();
+        assert!(code
+            .contains("pub a: ::std::borrow::Cow<'static, [::std::borrow::Cow<'static, [i64]>]>,"));
+
+        let tables = r#"{ "servers": [{ "ports": [1, 2] }, { "ports": [3] }] }"#;
+        assert!(matches!(
+            generate_struct_from_source(tables, &fixed),
+            Err(GenerationError::MismatchedArrayLengths(key)) if key == "servers"
+        ));
+        assert!(generate_struct_from_source(tables, &slices).is_ok());
+
+        let uniform = r#"{ "a": [[1, 2], [3, 4]] }"#;
+        let code = generate_struct_from_source(uniform, &fixed).unwrap();
+        assert!(code.contains("pub a: [[i64; 2]; 2],"));
+    }
+
+    #[cfg(all(
+        feature = "json-parsing",
+        feature = "ron-parsing",
+        feature = "toml-parsing",
+        feature = "yaml-parsing"
+    ))]
+    #[test]
+    fn error_locations() {
+        let cases = [
+            (Format::Json, "{\n  \"a\": 1,\n  \"b\": ]\n}", 3),
+            (Format::Ron, "(\n  a: 1,\n  b: ]\n)", 3),
+            (Format::Toml, "a = 1\nb = ]\n", 2),
+            (Format::Yaml, "a: 1\nb: [\n", 3),
+        ];
+
+        for (format, source, line) in cases {
+            let options = StructOptions {
+                format: Some(format),
+                ..StructOptions::default()
+            };
+            let error = match generate_struct_from_source(source, &options) {
+                Err(GenerationError::DeserializationFailed(error)) => error,
+                result => panic!("{format:?}: expected a deserialization error, got {result:?}"),
+            };
+            let location = error.location.as_ref().expect("a location");
+            assert_eq!(location.line, line, "{format:?}: {error}");
+            assert_eq!(location.snippet, source.lines().nth(line - 1).unwrap_or(""));
+        }
+    }
+
+    #[cfg(all(feature = "toml-parsing", feature = "json-parsing"))]
+    #[test]
+    fn flighting() {
+        let dir = TempDir::new("flighting");
+        let base = dir.join("config.toml");
+        let flights = dir.join("app.settingsconfig.json");
+        std::fs::write(&base, "port = 80\n[db]\nhost = 'h'\n").unwrap();
+        std::fs::write(
+            &flights,
+            r#"{ "settings": { "port": 81 }, "flights": [{ "name": "NewDb", "settings": { "db.host": "new" } }] }"#,
+        )
+        .unwrap();
+
+        let tokens =
+            generate_struct_tokens_from_layers(&[&base, &flights], &StructOptions::default())
+                .unwrap()
+                .to_string();
+        assert!(tokens.contains(&quote! { port: 81, }.to_string()));
+        assert!(tokens.contains(&quote! { host: ::std::borrow::Cow::Borrowed("new"), }.to_string()));
+
+        let dynamic = StructOptions {
+            generate_load_fns: true,
+            ..StructOptions::default()
+        };
+        assert!(matches!(
+            generate_struct_from_layers(&[&base, &flights], &dynamic),
+            Err(Error::Generation(GenerationError::DynamicFlighting))
+        ));
+        let static_only = StructOptions {
+            dynamic_loading: DynamicLoading::Never,
+            ..dynamic
+        };
+        assert!(generate_struct_from_layers(&[&base, &flights], &static_only).is_ok());
+
+        assert!(matches!(
+            generate_struct_from_layers(&[&flights, &base], &StructOptions::default()),
+            Err(Error::Generation(GenerationError::InvalidFlighting(_)))
+        ));
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn out_dir() {
+        let dir = TempDir::new("out");
+        let source = dir.join("config.toml");
+        std::fs::write(&source, "number = 1").unwrap();
+        let overlay = dir.join("config.dev.toml");
+        std::fs::write(&overlay, "number = 2").unwrap();
+
+        let create = |out_dir: Option<&Path>,
+                      filepaths: &[&Path],
+                      filename: &str,
+                      options: &StructOptions| {
+            let mut directives = Vec::new();
+            let destination = create_struct_from_layers_in_out_dir_with_directives(
+                filepaths,
+                out_dir.map(|dir| dir.as_os_str().to_owned()),
+                Path::new(filename),
+                options,
+                &mut directives,
+            )?;
+            Ok::<_, Error>((destination, String::from_utf8(directives).unwrap()))
+        };
+
+        let result = create(None, &[&source], "config.rs", &StructOptions::default());
+        assert!(matches!(result, Err(Error::MissingOutDir)));
+
+        let (destination, _) = create(
+            Some(&dir),
+            &[&source],
+            "config.rs",
+            &StructOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(destination, dir.join("config.rs"));
+        let code = std::fs::read_to_string(&destination).unwrap();
+        assert!(code.starts_with("#[allow(dead_code)]\n#[derive(Debug, Clone)]"));
+        assert!(!code.contains("#!"));
+
+        let options = StructOptions {
+            emit_rerun_if_changed: true,
+            ..StructOptions::default()
+        };
+        let (destination, directives) =
+            create(Some(&dir), &[&source], "single.rs", &options).unwrap();
+        assert_eq!(destination, dir.join("single.rs"));
+        assert_eq!(
+            directives,
+            format!("cargo:rerun-if-changed={}\n", source.display())
+        );
+
+        let (layered, _) =
+            create(Some(&dir), &[&source, &overlay], "layered.rs", &options).unwrap();
+        let code = std::fs::read_to_string(&layered).unwrap();
+        assert!(code.contains("number: 2"));
+        assert!(!code.contains("#!"));
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn escaped_json_strings() {
+        let code = generate(
+            r#"{"text": "quote \" backslash \\ newline \n tab \t unicode \u00e9 😇", "bell": "\u0007"}"#,
+            Format::Json,
+        );
+        assert!(code.contains(ESCAPED));
+        assert!(code.contains(r#"Cow::Borrowed("\u{7}")"#));
+    }
+
+    #[cfg(feature = "ron-parsing")]
+    #[test]
+    fn escaped_ron_strings() {
+        let code = generate(
+            r#"(text: "quote \" backslash \\ newline \n tab \t unicode é 😇", letter: '\'')"#,
+            Format::Ron,
+        );
+        assert!(code.contains(ESCAPED));
+        assert!(code.contains(r"letter: '\'',"));
+    }
+
+    #[cfg(feature = "toml-parsing")]
+    #[test]
+    fn escaped_toml_strings() {
+        let code = generate(
+            r#"
+                text = "quote \" backslash \\ newline \n tab \t unicode \u00E9 😇"
+                bell = "\u0007"
+            "#,
+            Format::Toml,
+        );
+        assert!(code.contains(ESCAPED));
+        assert!(code.contains(r#"Cow::Borrowed("\u{7}")"#));
+    }
+
+    #[cfg(feature = "yaml-parsing")]
+    #[test]
+    fn escaped_yaml_strings() {
+        let code = generate(
+            r#"
+text: "quote \" backslash \\ newline \n tab \t unicode \xe9 😇"
+bell: "\a"
+"#,
+            Format::Yaml,
+        );
+        assert!(code.contains(ESCAPED));
+        assert!(code.contains(r#"Cow::Borrowed("\u{7}")"#));
+    }
+}
@@ -0,0 +1,226 @@
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use super::cperror::SynchronizationError;
+use super::options::{ConfigParserOptions, SynchronizationScope};
+
+/// The extension of synchronization files.
+pub const SYNCHRONIZATION_FILE_EXTENSION: &str = "settingsconfig.json";
+
+/// Resolves the synchronization file of the app, looking up environment
+/// variables with `env` when a default base directory is needed.
+pub fn file_path(
+    options: &ConfigParserOptions,
+    env: &dyn Fn(&str) -> Option<OsString>,
+) -> Result<Option<PathBuf>, SynchronizationError> {
+    let scope = match options.synchronization_scope {
+        Some(scope) => scope,
+        None => return Ok(None),
+    };
+
+    let app_name = options.app_name.trim();
+    if !valid_app_name(app_name) {
+        return Err(SynchronizationError::InvalidAppName(
+            options.app_name.clone(),
+        ));
+    }
+
+    let configured = match scope {
+        SynchronizationScope::Test => options.test_directory.clone(),
+        SynchronizationScope::Prod => options.prod_directory.clone(),
+    };
+    let base = match configured {
+        Some(base) => base,
+        None => default_base_directory(scope, env)
+            .ok_or(SynchronizationError::MissingBaseDirectory(scope))?,
+    };
+
+    Ok(Some(base.join(file_name(app_name, scope))))
+}
+
+/// The name of the synchronization file of the app in the scope. These are
+/// the names the files have always had, so existing files are still found.
+pub fn file_name(app_name: &str, scope: SynchronizationScope) -> String {
+    match scope {
+        SynchronizationScope::Test => format!("{app_name}.json"),
+        SynchronizationScope::Prod => {
+            format!("Substrate_{app_name}_V2.{SYNCHRONIZATION_FILE_EXTENSION}")
+        }
+    }
+}
+
+/// Creates the synchronization file, and any missing directories above it,
+/// unless it already exists.
+pub fn ensure_exists(filepath: &std::path::Path) -> Result<(), SynchronizationError> {
+    if let Some(parent) = filepath.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(filepath)
+    {
+        Ok(_) => Ok(()),
+        Err(ref error) if error.kind() == ErrorKind::AlreadyExists => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// App names become file names, so they are restricted to
+/// characters which are safe in paths on every platform.
+pub fn valid_app_name(app_name: &str) -> bool {
+    !app_name.is_empty()
+        && app_name.len() <= 64
+        && !app_name.starts_with('.')
+        && app_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// The directory that synchronization files are kept in by default.
+///
+/// Test files live under the system temp directory. Prod files live in the
+/// user's config directory: `$XDG_CONFIG_HOME` (or `~/.config`) on Linux,
+/// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows.
+pub fn default_base_directory(
+    scope: SynchronizationScope,
+    env: &dyn Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+    let non_empty = |name: &str| {
+        env(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+
+    match scope {
+        SynchronizationScope::Test => Some(std::env::temp_dir().join("config_struct")),
+        SynchronizationScope::Prod => {
+            if cfg!(windows) {
+                non_empty("APPDATA")
+            } else if cfg!(target_os = "macos") {
+                non_empty("HOME").map(|home| home.join("Library").join("Application Support"))
+            } else {
+                non_empty("XDG_CONFIG_HOME")
+                    .filter(|path| path.is_absolute())
+                    .or_else(|| non_empty("HOME").map(|home| home.join(".config")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn options(app_name: &str, scope: Option<SynchronizationScope>) -> ConfigParserOptions {
+        ConfigParserOptions {
+            app_name: app_name.to_owned(),
+            synchronization_scope: scope,
+            ..ConfigParserOptions::default()
+        }
+    }
+
+    fn no_env(_: &str) -> Option<OsString> {
+        None
+    }
+
+    #[test]
+    fn app_names() {
+        assert!(valid_app_name("my-app_2.0"));
+        assert!(!valid_app_name(""));
+        assert!(!valid_app_name(".hidden"));
+        assert!(!valid_app_name(".."));
+        assert!(!valid_app_name("../escape"));
+        assert!(!valid_app_name("a/b"));
+        assert!(!valid_app_name("a\\b"));
+        assert!(!valid_app_name("C:app"));
+        assert!(!valid_app_name(&"a".repeat(65)));
+
+        let error = file_path(&options(" ", Some(SynchronizationScope::Test)), &no_env);
+        assert!(matches!(
+            error,
+            Err(SynchronizationError::InvalidAppName(_))
+        ));
+    }
+
+    #[test]
+    fn configured_directories() {
+        let dir = TempDir::new("sync");
+        let options = ConfigParserOptions {
+            test_directory: Some(dir.join("test")),
+            prod_directory: Some(dir.join("prod")),
+            ..options("app", None)
+        };
+
+        assert_eq!(file_path(&options, &no_env).unwrap(), None);
+
+        let test = ConfigParserOptions {
+            synchronization_scope: Some(SynchronizationScope::Test),
+            ..options.clone()
+        };
+        let test_path = file_path(&test, &no_env).unwrap().unwrap();
+        assert_eq!(test_path, dir.join("test/app.json"));
+
+        let prod = ConfigParserOptions {
+            synchronization_scope: Some(SynchronizationScope::Prod),
+            ..options
+        };
+        let prod_path = file_path(&prod, &no_env).unwrap().unwrap();
+        assert_eq!(
+            prod_path,
+            dir.join("prod/Substrate_app_V2.settingsconfig.json")
+        );
+
+        // Resolving the path has no side effects.
+        assert!(!dir.join("prod").exists());
+
+        ensure_exists(&prod_path).unwrap();
+        assert!(prod_path.is_file());
+
+        // Existing files are left alone.
+        std::fs::write(&prod_path, "{}").unwrap();
+        ensure_exists(&prod_path).unwrap();
+        assert_eq!(std::fs::read_to_string(&prod_path).unwrap(), "{}");
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn xdg_defaults() {
+        let prod = options("app", Some(SynchronizationScope::Prod));
+
+        let xdg = |name: &str| match name {
+            "XDG_CONFIG_HOME" => Some("/xdg".into()),
+            "HOME" => Some("/home/user".into()),
+            _ => None,
+        };
+        assert_eq!(
+            file_path(&prod, &xdg).unwrap(),
+            Some(PathBuf::from("/xdg/Substrate_app_V2.settingsconfig.json"))
+        );
+
+        // Relative values of XDG variables must be ignored.
+        let home = |name: &str| match name {
+            "XDG_CONFIG_HOME" => Some("relative".into()),
+            "HOME" => Some("/home/user".into()),
+            _ => None,
+        };
+        assert_eq!(
+            file_path(&prod, &home).unwrap(),
+            Some(PathBuf::from(
+                "/home/user/.config/Substrate_app_V2.settingsconfig.json"
+            ))
+        );
+
+        let error = file_path(&prod, &no_env);
+        assert!(matches!(
+            error,
+            Err(SynchronizationError::MissingBaseDirectory(
+                SynchronizationScope::Prod
+            ))
+        ));
+    }
+}
@@ -1,7 +1,8 @@
 use toml::{self, Value};
 
 use super::{
-    cperror::GenerationError,
+    cperror::{DeserializationError, GenerationError},
+    key_order::KeyOrder,
     options::StructOptions,
     parsing,
     value::{GenericStruct, GenericValue},
@@ -10,10 +11,17 @@ use super::{
 pub fn parse_toml(toml: &str, options: &StructOptions) -> Result<GenericStruct, GenerationError> {
     use parsing::ParsedFields;
 
-    let toml_struct: ParsedFields<Value> = toml::from_str(toml)
-        .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+    let toml_struct: ParsedFields<Value> =
+        toml::from_str(toml).map_err(|err| toml_error(err, toml))?;
 
-    let generic_struct = parsing::parsed_to_generic_struct(toml_struct, options, toml_to_raw_value);
+    let mut generic_struct =
+        parsing::parsed_to_generic_struct(toml_struct, options, toml_to_raw_value)?;
+
+    // Tables are parsed into sorted maps, so the key order is read separately.
+    if options.preserve_key_order {
+        let key_order: KeyOrder = toml::from_str(toml).map_err(|err| toml_error(err, toml))?;
+        key_order.apply(&mut generic_struct);
+    }
 
     Ok(generic_struct)
 }
@@ -21,10 +29,11 @@ pub fn parse_toml(toml: &str, options: &StructOptions) -> Result<GenericStruct,
 fn toml_to_raw_value(
     super_struct: &str,
     super_key: &str,
+    key_path: &str,
     value: Value,
     options: &StructOptions,
-) -> GenericValue {
-    match value {
+) -> Result<GenericValue, GenerationError> {
+    let value = match value {
         Value::Boolean(value) => GenericValue::Bool(value),
         Value::Integer(value) => parsing::preferred_int(value, options.default_int_size),
         Value::Float(value) => parsing::preferred_float(value, options.default_float_size),
@@ -33,32 +42,49 @@ fn toml_to_raw_value(
         Value::Array(values) => GenericValue::Array(
             values
                 .into_iter()
-                .map(|value| toml_to_raw_value(super_struct, super_key, value, options))
-                .collect(),
+                .enumerate()
+                .map(|(index, value)| {
+                    let element_path = parsing::element_path(key_path, index);
+                    toml_to_raw_value(super_struct, super_key, &element_path, value, options)
+                })
+                .collect::<Result<_, _>>()?,
         ),
         Value::Table(values) => {
             let sub_struct_name = format!("{super_struct}__{super_key}");
             let values = values
                 .into_iter()
                 .map(|(key, value)| {
-                    let value = toml_to_raw_value(&sub_struct_name, &key, value, options);
-                    (key, value)
+                    let field_path = parsing::field_path(key_path, &key);
+                    let value =
+                        toml_to_raw_value(&sub_struct_name, &key, &field_path, value, options)?;
+                    Ok((key, value))
                 })
-                .collect();
+                .collect::<Result<_, GenerationError>>()?;
             GenericValue::Struct(GenericStruct {
                 struct_name: sub_struct_name,
                 fields: values,
+                ..GenericStruct::default()
             })
         }
-    }
+    };
+
+    Ok(value)
 }
 
-#[allow(dead_code)]
 pub fn parse_map_keys(toml: &str) -> Result<Vec<String>, GenerationError> {
     use linear_map::LinearMap;
 
-    let map: LinearMap<String, Value> = toml::from_str(toml)
-        .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+    let map: LinearMap<String, Value> =
+        toml::from_str(toml).map_err(|err| toml_error(err, toml))?;
 
     Ok(map.into_iter().map(|pair| pair.0).collect())
 }
+
+/// Converts a TOML parse error, pointing at its location in the config.
+pub fn toml_error(error: toml::de::Error, toml: &str) -> GenerationError {
+    // The TOML parser counts lines and columns from 0.
+    let line_column = error
+        .line_col()
+        .map(|(line, column)| (line + 1, column + 1));
+    DeserializationError::from_parser(error, toml, line_column).into()
+}
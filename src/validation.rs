@@ -1,9 +1,19 @@
-use super::cperror::GenerationError;
-use super::generation::type_string;
+use std::collections::BTreeMap;
+
+use super::cperror::{Diagnostic, GenerationError, ValidationReport};
+use super::naming;
+use super::options::{Edition, StructOptions};
+use super::parsing;
 use super::value::{GenericStruct, GenericValue};
 
+/// Whether `name` can be used as an identifier in the given edition: it
+/// has the right characters, and is not a keyword.
+pub fn valid_identifier(name: &str, edition: Edition) -> bool {
+    identifier_syntax(name) && !is_keyword(name, edition)
+}
+
 #[allow(clippy::is_digit_ascii_radix)]
-pub fn valid_identifier(name: &str) -> bool {
+fn identifier_syntax(name: &str) -> bool {
     let good_start = name.starts_with(|c: char| c == '_' || (c.is_ascii() && c.is_alphabetic()));
     let good_end = !name
         .contains(|c: char| !(c == '_' || c.is_digit(10) || (c.is_ascii() && c.is_alphabetic())));
@@ -11,48 +21,248 @@ pub fn valid_identifier(name: &str) -> bool {
     good_start && good_end && name != "_"
 }
 
-pub fn validate_struct(struct_value: &GenericStruct) -> Result<(), GenerationError> {
-    for (key, value) in &struct_value.fields {
-        validate_field_name(key)?;
-        validate_value(key, value)?;
+/// The strict and reserved keywords of Rust 2015.
+const KEYWORDS_2015: &[&str] = &[
+    "Self", "abstract", "as", "become", "box", "break", "const", "continue", "crate", "do", "else",
+    "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static",
+    "struct", "super", "trait", "true", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// The keywords added by Rust 2018.
+const KEYWORDS_2018: &[&str] = &["async", "await", "dyn", "try"];
+
+/// The keywords added by Rust 2024.
+const KEYWORDS_2024: &[&str] = &["gen"];
+
+/// Whether `name` is a keyword in the given edition, and so can only be
+/// used as an identifier in its raw form, like `r#type`.
+///
+/// Weak keywords such as `union` are only keywords in certain positions,
+/// so they are valid identifiers.
+pub fn is_keyword(name: &str, edition: Edition) -> bool {
+    KEYWORDS_2015.contains(&name)
+        || (edition >= Edition::Rust2018 && KEYWORDS_2018.contains(&name))
+        || (edition >= Edition::Rust2024 && KEYWORDS_2024.contains(&name))
+}
+
+/// Whether `name` can be written as a raw identifier. Path keywords such
+/// as `self` cannot be, even though they are keywords.
+pub fn can_be_raw(name: &str) -> bool {
+    !matches!(name, "self" | "Self" | "super" | "crate")
+}
+
+/// Whether `path` is a valid Rust path such as `Debug` or `::serde::Serialize`.
+pub fn valid_path(path: &str, edition: Edition) -> bool {
+    let path = path.trim();
+    let (global, path) = match path.strip_prefix("::") {
+        Some(path) => (true, path),
+        None => (false, path),
+    };
+    let segments: Vec<&str> = path.split("::").map(str::trim).collect();
+    let Some((name, modules)) = segments.split_last() else {
+        return false;
+    };
+
+    // `crate` and `self` can only start a path, and `super` can only start
+    // one or follow `self` or another `super`.
+    let mut leading = !global;
+    for (index, &segment) in modules.iter().enumerate() {
+        let valid = match segment {
+            "crate" | "self" => leading && index == 0,
+            "super" => leading && (index == 0 || modules[index - 1] != "crate"),
+            _ => {
+                leading = false;
+                valid_identifier(segment, edition)
+            }
+        };
+        if !valid {
+            return false;
+        }
     }
-    Ok(())
+
+    valid_identifier(name, edition)
 }
 
-fn validate_field_name(field_name: &str) -> Result<(), GenerationError> {
-    if valid_identifier(field_name) {
+/// Checks that the struct can be generated, failing with the first problem
+/// found.
+pub fn validate_struct(
+    struct_value: &GenericStruct,
+    options: &StructOptions,
+) -> Result<(), GenerationError> {
+    match diagnose_struct(struct_value, options).into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.error),
+        None => Ok(()),
+    }
+}
+
+/// Checks that the struct can be generated, failing with a report of every
+/// problem found.
+pub fn validate_struct_fully(
+    struct_value: &GenericStruct,
+    options: &StructOptions,
+) -> Result<(), GenerationError> {
+    let diagnostics = diagnose_struct(struct_value, options);
+    if diagnostics.is_empty() {
         Ok(())
     } else {
-        Err(GenerationError::InvalidFieldName(field_name.into()))
+        Err(GenerationError::InvalidConfig(ValidationReport {
+            diagnostics,
+        }))
+    }
+}
+
+/// Walks the whole struct, collecting every problem along with the key
+/// path of the value that caused it, such as `servers[3].ports`.
+fn diagnose_struct(struct_value: &GenericStruct, options: &StructOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    diagnose_fields(struct_value, "", options, &mut diagnostics);
+    diagnostics
+}
+
+fn diagnose_fields(
+    struct_value: &GenericStruct,
+    path: &str,
+    options: &StructOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    // The first key to produce each field name, to catch keys such as
+    // `maxSize` and `max_size` which only differ before being converted.
+    let mut field_keys = BTreeMap::new();
+
+    for (key, value) in &struct_value.fields {
+        let key_path = parsing::field_path(path, key);
+
+        let result = validate_field_name(key, options).and_then(|()| {
+            let field_name = naming::field_name(key, options);
+            match field_keys.get(&field_name) {
+                Some(&first_key) => Err(GenerationError::DuplicateFieldName {
+                    key_path: path.to_owned(),
+                    first_key: String::clone(first_key),
+                    second_key: key.clone(),
+                    field_name,
+                }),
+                None => {
+                    field_keys.insert(field_name, key);
+                    Ok(())
+                }
+            }
+        });
+        if let Err(error) = result {
+            diagnostics.push(Diagnostic {
+                key_path: key_path.clone(),
+                error,
+            });
+        }
+        diagnose_value(value, &key_path, options, diagnostics);
     }
 }
 
-fn validate_value(key: &str, value: &GenericValue) -> Result<(), GenerationError> {
-    match *value {
-        GenericValue::Option(Some(ref value)) => validate_value(key, value)?,
-        GenericValue::Array(ref values) => {
-            validate_array_element_types(key, values)?;
-            for value in values {
-                validate_value(key, value)?;
+fn diagnose_value(
+    value: &GenericValue,
+    path: &str,
+    options: &StructOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match value {
+        GenericValue::Option(Some(value)) => diagnose_value(value, path, options, diagnostics),
+        GenericValue::Array(values) => {
+            let found = diagnostics.len();
+            for (index, value) in values.iter().enumerate() {
+                let element_path = parsing::element_path(path, index);
+                diagnose_value(value, &element_path, options, diagnostics);
+            }
+
+            // Problems inside the elements usually make them incompatible with
+            // each other too, so only report the array itself if they are fine.
+            if diagnostics.len() == found {
+                let result = validate_array_element_types(path, values).and_then(|()| {
+                    // The unified type of the elements can only give nested
+                    // arrays a fixed size if they all have the same length.
+                    if options.max_array_size > 0
+                        && !parsing::uniform_lengths(values.iter().collect())
+                    {
+                        Err(GenerationError::MismatchedArrayLengths(path.to_owned()))
+                    } else {
+                        Ok(())
+                    }
+                });
+                if let Err(error) = result {
+                    diagnostics.push(Diagnostic {
+                        key_path: path.to_owned(),
+                        error,
+                    });
+                }
             }
         }
-        GenericValue::Struct(ref value) => validate_struct(value)?,
+        GenericValue::Struct(value) => diagnose_fields(value, path, options, diagnostics),
         _ => (),
     }
+}
+
+pub fn validate_keys(keys: &[String], edition: Edition) -> Result<(), GenerationError> {
+    for key in keys {
+        validate_variant_name(key, edition)?;
+    }
     Ok(())
 }
 
-#[allow(clippy::get_first)]
-fn validate_array_element_types(key: &str, values: &[GenericValue]) -> Result<(), GenerationError> {
-    if let Some(value) = values.get(0) {
-        // TODO: A more efficient way to compare types would be nice
-        let candidate = type_string(value);
-        let all_same_type = values.iter().map(type_string).all(|s| s == candidate);
+pub fn validate_variant_data(
+    data: &[GenericValue],
+    options: &StructOptions,
+) -> Result<(), GenerationError> {
+    if validate_array_element_types("", data).is_err() {
+        return Err(GenerationError::HeterogenousVariantData);
+    }
+    for value in data {
+        validate_value("", value, options)?;
+    }
+    Ok(())
+}
 
-        if !all_same_type {
-            return Err(GenerationError::HeterogenousArray(key.into()));
-        }
+fn validate_variant_name(variant_name: &str, edition: Edition) -> Result<(), GenerationError> {
+    if valid_identifier(variant_name, edition) {
+        Ok(())
+    } else {
+        Err(GenerationError::InvalidVariantName(variant_name.into()))
     }
+}
+
+fn validate_field_name(field_name: &str, options: &StructOptions) -> Result<(), GenerationError> {
+    let valid = if options.sanitize_keys {
+        naming::sanitize_key(field_name).is_some()
+    } else {
+        // The key itself must look like an identifier, but it is the field
+        // name it converts to which must not be a keyword.
+        let converted = naming::field_name(field_name, options);
+        identifier_syntax(field_name)
+            && (!is_keyword(&converted, options.edition)
+                || (options.raw_identifiers && can_be_raw(&converted)))
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(GenerationError::InvalidFieldName(field_name.into()))
+    }
+}
+
+fn validate_value(
+    key: &str,
+    value: &GenericValue,
+    options: &StructOptions,
+) -> Result<(), GenerationError> {
+    let mut diagnostics = Vec::new();
+    diagnose_value(value, key, options, &mut diagnostics);
+    match diagnostics.into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.error),
+        None => Ok(()),
+    }
+}
+
+fn validate_array_element_types(key: &str, values: &[GenericValue]) -> Result<(), GenerationError> {
+    parsing::unified_type(key, values)?;
     Ok(())
 }
 
@@ -63,25 +273,135 @@ mod tests {
     #[allow(clippy::bool_assert_comparison)]
     #[test]
     fn valid_field_names() {
-        assert_eq!(valid_identifier("x"), true);
-        assert_eq!(valid_identifier("word"), true);
-        assert_eq!(valid_identifier("two_words"), true);
-        assert_eq!(valid_identifier("PascalCase"), true);
-        assert_eq!(valid_identifier("number_150"), true);
-        assert_eq!(valid_identifier("_private"), true);
-        assert_eq!(valid_identifier("____very_private__"), true);
+        assert_eq!(valid_identifier("x", Edition::Rust2024), true);
+        assert_eq!(valid_identifier("word", Edition::Rust2024), true);
+        assert_eq!(valid_identifier("two_words", Edition::Rust2024), true);
+        assert_eq!(valid_identifier("PascalCase", Edition::Rust2024), true);
+        assert_eq!(valid_identifier("number_150", Edition::Rust2024), true);
+        assert_eq!(valid_identifier("_private", Edition::Rust2024), true);
+        assert_eq!(
+            valid_identifier("____very_private__", Edition::Rust2024),
+            true
+        );
     }
 
     #[test]
     #[allow(clippy::bool_assert_comparison)]
     fn invalid_field_names() {
-        assert_eq!(valid_identifier(""), false);
-        assert_eq!(valid_identifier("_"), false);
-        assert_eq!(valid_identifier("100_number_before"), false);
-        assert_eq!(valid_identifier("white space"), false);
-        assert_eq!(valid_identifier("wierd*characters??"), false);
-        assert_eq!(valid_identifier("emoji😇"), false);
-        assert_eq!(valid_identifier("accénts"), false);
+        assert_eq!(valid_identifier("", Edition::Rust2024), false);
+        assert_eq!(valid_identifier("_", Edition::Rust2024), false);
+        assert_eq!(
+            valid_identifier("100_number_before", Edition::Rust2024),
+            false
+        );
+        assert_eq!(valid_identifier("white space", Edition::Rust2024), false);
+        assert_eq!(
+            valid_identifier("wierd*characters??", Edition::Rust2024),
+            false
+        );
+        assert_eq!(valid_identifier("emoji😇", Edition::Rust2024), false);
+        assert_eq!(valid_identifier("accénts", Edition::Rust2024), false);
+    }
+
+    #[test]
+    fn paths() {
+        assert!(valid_path("Debug", Edition::Rust2024));
+        assert!(valid_path("serde::Serialize", Edition::Rust2024));
+        assert!(valid_path("::std::hash::Hash", Edition::Rust2024));
+        assert!(!valid_path("", Edition::Rust2024));
+        assert!(!valid_path("serde::", Edition::Rust2024));
+        assert!(!valid_path("Vec<u8>", Edition::Rust2024));
+        assert!(!valid_path("Debug, Clone", Edition::Rust2024));
+        assert!(valid_path("crate::traits::Custom", Edition::Rust2024));
+        assert!(valid_path("self::Custom", Edition::Rust2024));
+        assert!(!valid_path("type::Custom", Edition::Rust2024));
+
+        // Path keywords are only allowed at the start.
+        assert!(valid_path("super::Custom", Edition::Rust2024));
+        assert!(valid_path("super::super::Custom", Edition::Rust2024));
+        assert!(valid_path("self::super::Custom", Edition::Rust2024));
+        assert!(!valid_path("crate::super::Custom", Edition::Rust2024));
+        assert!(!valid_path("traits::crate::Custom", Edition::Rust2024));
+        assert!(!valid_path("traits::self::Custom", Edition::Rust2024));
+        assert!(!valid_path("traits::super::Custom", Edition::Rust2024));
+        assert!(!valid_path("crate::self::Custom", Edition::Rust2024));
+        assert!(!valid_path("::crate::Custom", Edition::Rust2024));
+        assert!(!valid_path("Self::Custom", Edition::Rust2024));
+        assert!(!valid_path("traits::Self", Edition::Rust2024));
+
+        // The last segment is the trait itself.
+        assert!(!valid_path("crate", Edition::Rust2024));
+        assert!(!valid_path("self::super", Edition::Rust2024));
+    }
+
+    #[test]
+    fn keywords() {
+        for name in [
+            "fn", "match", "type", "self", "Self", "crate", "super", "box", "yield",
+        ] {
+            assert!(!valid_identifier(name, Edition::Rust2015), "{name}");
+        }
+
+        // Keywords added by later editions are only keywords from then on.
+        assert!(valid_identifier("async", Edition::Rust2015));
+        assert!(!valid_identifier("async", Edition::Rust2018));
+        assert!(valid_identifier("gen", Edition::Rust2021));
+        assert!(!valid_identifier("gen", Edition::Rust2024));
+
+        // Weak keywords are valid identifiers.
+        assert!(valid_identifier("union", Edition::Rust2024));
+        assert!(valid_identifier("macro_rules", Edition::Rust2024));
+        assert!(valid_identifier("Type", Edition::Rust2024));
+    }
+
+    #[cfg(feature = "json-parsing")]
+    #[test]
+    fn diagnostics() {
+        let config = crate::json_parsing::parse_json(
+            r#"{
+                "bad key": 1,
+                "servers": [
+                    { "ports": [80] },
+                    { "ports": [80, "http"], "2fa": true }
+                ],
+                "mixed": [1, "a"],
+                "nested": { "deeper": { "white space": [] } }
+            }"#,
+            &crate::options::StructOptions::default(),
+        )
+        .unwrap();
+
+        let report = match validate_struct_fully(&config, &Default::default()) {
+            Err(GenerationError::InvalidConfig(report)) => report,
+            result => panic!("expected a validation report, got {result:?}"),
+        };
+        let key_paths: Vec<&str> = report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.key_path.as_str())
+            .collect();
+        assert_eq!(
+            key_paths,
+            [
+                "bad key",
+                "servers[1].ports",
+                "servers[1].2fa",
+                "mixed",
+                "nested.deeper.white space",
+            ]
+        );
+        assert!(report
+            .to_string()
+            .starts_with("Found 5 problems in the config:\n"));
+        assert!(report
+            .to_string()
+            .contains("\n  servers[1].ports: Array under key `servers[1].ports` has elements"));
+
+        // The first problem is the same one reported without the report.
+        assert!(matches!(
+            validate_struct(&config, &Default::default()),
+            Err(GenerationError::InvalidFieldName(name)) if name == "bad key"
+        ));
     }
 
     fn validate_array_test(values: &[GenericValue]) -> Result<(), GenerationError> {
@@ -101,6 +421,31 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn arrays_of_tables_with_missing_fields() {
+        let table = |fields: Vec<(&str, GenericValue)>| {
+            GenericValue::Struct(GenericStruct {
+                struct_name: "Table".to_owned(),
+                fields: fields
+                    .into_iter()
+                    .map(|(key, value)| (key.to_owned(), value))
+                    .collect(),
+                ..GenericStruct::default()
+            })
+        };
+
+        validate_array_test(&[
+            table(vec![("a", GenericValue::I64(0))]),
+            table(vec![("b", GenericValue::Bool(true))]),
+        ])
+        .unwrap();
+        assert!(validate_array_test(&[
+            table(vec![("a", GenericValue::I64(0))]),
+            table(vec![("a", GenericValue::Bool(true))]),
+        ])
+        .is_err());
+    }
+
     #[test]
     fn heterogenous_arrays() {
         assert!(validate_array_test(&[GenericValue::Unit, GenericValue::I64(0)]).is_err());
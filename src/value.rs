@@ -1,10 +1,15 @@
+use linear_map::LinearMap;
 use std::collections::BTreeMap;
 
 /// Represents a Rust struct.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct GenericStruct {
     pub struct_name: String,
-    pub fields: BTreeMap<String, GenericValue>,
+    pub fields: LinearMap<String, GenericValue>,
+    /// Documentation for the struct itself.
+    pub doc: Option<String>,
+    /// Documentation for each field, by key.
+    pub field_docs: BTreeMap<String, String>,
 }
 
 /// Represents a typed Rust value.
@@ -18,6 +23,7 @@ pub enum GenericValue {
     I16(i16),
     I32(i32),
     I64(i64),
+    I128(i128),
     U8(u8),
     U16(u16),
     U32(u32),
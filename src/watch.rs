@@ -0,0 +1,269 @@
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error as StdError;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use super::options::SynchronizationScope;
+
+type LoadFn<T> = Box<dyn Fn(&Path) -> Result<T, Box<dyn StdError>> + Send>;
+
+/// A cheaply cloneable handle to the current value of a watched config.
+#[derive(Debug)]
+pub struct ConfigHandle<T> {
+    current: Arc<RwLock<Arc<T>>>,
+}
+
+impl<T> ConfigHandle<T> {
+    /// The most recently loaded config.
+    pub fn get(&self) -> Arc<T> {
+        match self.current.read() {
+            Ok(current) => Arc::clone(&current),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    fn set(&self, value: Arc<T>) {
+        match self.current.write() {
+            Ok(mut current) => *current = value,
+            Err(poisoned) => *poisoned.into_inner() = value,
+        }
+    }
+}
+
+impl<T> Clone for ConfigHandle<T> {
+    fn clone(&self) -> Self {
+        ConfigHandle {
+            current: Arc::clone(&self.current),
+        }
+    }
+}
+
+/// Reloads a config file whenever its contents change.
+///
+/// The file is polled in a background thread, which stops when the watcher
+/// is dropped. When the contents of the file change it is loaded again, and
+/// the new value is published to every [`ConfigHandle`](struct.ConfigHandle.html)
+/// and subscriber. If the new contents fail to load, the previous value is
+/// kept.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use config_to_struct::{ConfigWatcher, SynchronizationScope};
+///
+/// let watcher = ConfigWatcher::with_scope(
+///     "config.toml",
+///     SynchronizationScope::Prod,
+///     |path| Ok(Config::load_from(path)?.into_owned()))?;
+///
+/// let config = watcher.handle();
+/// println!("{}", config.get().name);
+/// ```
+pub struct ConfigWatcher<T> {
+    handle: ConfigHandle<T>,
+    state: Arc<Mutex<WatchState<T>>>,
+    stop: Option<Sender<()>>,
+}
+
+struct WatchState<T> {
+    filepath: PathBuf,
+    load: LoadFn<T>,
+    hash: u64,
+    subscribers: Vec<Sender<Arc<T>>>,
+}
+
+impl<T: Send + Sync + 'static> ConfigWatcher<T> {
+    /// Load the config with `load` and poll the file for changes at the
+    /// given interval.
+    ///
+    /// Fails if the config cannot be loaded initially.
+    pub fn new<P, F>(filepath: P, interval: Duration, load: F) -> Result<Self, Box<dyn StdError>>
+    where
+        P: Into<PathBuf>,
+        F: Fn(&Path) -> Result<T, Box<dyn StdError>> + Send + 'static,
+    {
+        let filepath = filepath.into();
+        let hash = content_hash(&filepath)?;
+        let value = load(&filepath)?;
+
+        let handle = ConfigHandle {
+            current: Arc::new(RwLock::new(Arc::new(value))),
+        };
+        let state = Arc::new(Mutex::new(WatchState {
+            filepath,
+            load: Box::new(load),
+            hash,
+            subscribers: Vec::new(),
+        }));
+
+        let (stop, stopped) = mpsc::channel();
+        let thread_handle = handle.clone();
+        let thread_state = Arc::clone(&state);
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                // Errors keep the previous value, and are retried when the
+                // file changes again.
+                let _ = poll(&thread_state, &thread_handle);
+            }
+        });
+
+        Ok(ConfigWatcher {
+            handle,
+            state,
+            stop: Some(stop),
+        })
+    }
+
+    /// Load the config with `load` and poll the file for changes at the
+    /// interval of the given scope.
+    pub fn with_scope<P, F>(
+        filepath: P,
+        scope: SynchronizationScope,
+        load: F,
+    ) -> Result<Self, Box<dyn StdError>>
+    where
+        P: Into<PathBuf>,
+        F: Fn(&Path) -> Result<T, Box<dyn StdError>> + Send + 'static,
+    {
+        Self::new(filepath, scope.polling_interval(), load)
+    }
+}
+
+impl<T> ConfigWatcher<T> {
+    /// A handle to the current config, which is updated on every reload.
+    pub fn handle(&self) -> ConfigHandle<T> {
+        self.handle.clone()
+    }
+
+    /// The most recently loaded config.
+    pub fn get(&self) -> Arc<T> {
+        self.handle.get()
+    }
+
+    /// A channel which receives the new config after every reload.
+    pub fn subscribe(&self) -> Receiver<Arc<T>> {
+        let (sender, receiver) = mpsc::channel();
+        lock(&self.state).subscribers.push(sender);
+        receiver
+    }
+
+    /// Check the file for changes immediately, instead of waiting for
+    /// the next poll.
+    ///
+    /// Returns whether the config was reloaded, or the error if the
+    /// changed file failed to load.
+    pub fn poll(&self) -> Result<bool, Box<dyn StdError>> {
+        poll(&self.state, &self.handle)
+    }
+}
+
+impl<T> Drop for ConfigWatcher<T> {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the polling thread so that it stops.
+        self.stop.take();
+    }
+}
+
+impl<T> std::fmt::Debug for ConfigWatcher<T> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter
+            .debug_struct("ConfigWatcher")
+            .field("filepath", &lock(&self.state).filepath)
+            .finish()
+    }
+}
+
+fn poll<T>(
+    state: &Mutex<WatchState<T>>,
+    handle: &ConfigHandle<T>,
+) -> Result<bool, Box<dyn StdError>> {
+    let mut state = lock(state);
+
+    let hash = content_hash(&state.filepath)?;
+    if hash == state.hash {
+        return Ok(false);
+    }
+    // Don't retry the same contents if they fail to load.
+    state.hash = hash;
+
+    let value = Arc::new((state.load)(&state.filepath)?);
+    handle.set(Arc::clone(&value));
+    state
+        .subscribers
+        .retain(|subscriber| subscriber.send(Arc::clone(&value)).is_ok());
+
+    Ok(true)
+}
+
+fn content_hash(filepath: &Path) -> Result<u64, Box<dyn StdError>> {
+    let contents = std::fs::read(filepath)?;
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+fn lock<T>(state: &Mutex<WatchState<T>>) -> std::sync::MutexGuard<'_, WatchState<T>> {
+    match state.lock() {
+        Ok(state) => state,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn load(path: &Path) -> Result<i64, Box<dyn StdError>> {
+        Ok(std::fs::read_to_string(path)?.trim().parse()?)
+    }
+
+    #[test]
+    fn reloads() {
+        let dir = TempDir::new("watch");
+        let filepath = dir.join("config.txt");
+        std::fs::write(&filepath, "1").unwrap();
+
+        let watcher = ConfigWatcher::new(&filepath, Duration::from_secs(3600), load).unwrap();
+        let handle = watcher.handle();
+        let changes = watcher.subscribe();
+        assert_eq!(*handle.get(), 1);
+        assert!(!watcher.poll().unwrap());
+
+        std::fs::write(&filepath, "2").unwrap();
+        assert!(watcher.poll().unwrap());
+        assert_eq!(*handle.get(), 2);
+        assert_eq!(*changes.try_recv().unwrap(), 2);
+
+        std::fs::write(&filepath, "two").unwrap();
+        assert!(watcher.poll().is_err());
+        assert!(!watcher.poll().unwrap());
+        assert_eq!(*handle.get(), 2);
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[test]
+    fn polls_in_background() {
+        let dir = TempDir::new("poll");
+        let filepath = dir.join("config.txt");
+        std::fs::write(&filepath, "1").unwrap();
+
+        let watcher = ConfigWatcher::new(&filepath, Duration::from_millis(10), load).unwrap();
+        let changes = watcher.subscribe();
+        std::fs::write(&filepath, "3").unwrap();
+
+        let value = changes.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(*value, 3);
+        assert_eq!(*watcher.get(), 3);
+    }
+
+    #[test]
+    fn scope_intervals() {
+        assert!(SynchronizationScope::Test.polling_interval() < Duration::from_secs(45));
+        assert!(SynchronizationScope::Prod.polling_interval() >= Duration::from_secs(15 * 60));
+    }
+}
@@ -1,7 +1,7 @@
 use serde_yaml::{self, Value};
 
 use super::{
-    cperror::GenerationError,
+    cperror::{DeserializationError, GenerationError},
     options::StructOptions,
     parsing,
     value::{GenericStruct, GenericValue},
@@ -10,10 +10,11 @@ use super::{
 pub fn parse_yaml(yaml: &str, options: &StructOptions) -> Result<GenericStruct, GenerationError> {
     use parsing::ParsedFields;
 
-    let yaml_struct: ParsedFields<Value> = serde_yaml::from_str(yaml)
-        .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+    let yaml_struct: ParsedFields<Value> =
+        serde_yaml::from_str(yaml).map_err(|err| yaml_error(err, yaml))?;
 
-    let generic_struct = parsing::parsed_to_generic_struct(yaml_struct, options, yaml_to_raw_value);
+    let generic_struct =
+        parsing::parsed_to_generic_struct(yaml_struct, options, yaml_to_raw_value)?;
 
     Ok(generic_struct)
 }
@@ -21,50 +22,105 @@ pub fn parse_yaml(yaml: &str, options: &StructOptions) -> Result<GenericStruct,
 fn yaml_to_raw_value(
     super_struct: &str,
     super_key: &str,
+    key_path: &str,
     value: Value,
     options: &StructOptions,
-) -> GenericValue {
-    match value {
+) -> Result<GenericValue, GenerationError> {
+    let value = match value {
         Value::Null => GenericValue::Option(None),
         Value::Bool(value) => GenericValue::Bool(value),
         Value::Number(value) => match (value.as_i64(), value.as_u64(), value.as_f64()) {
-            // TODO: Add some unit tests for this
             (Some(x), _, _) => parsing::preferred_int(x, options.default_int_size),
             (None, Some(x), _) => GenericValue::U64(x),
             (None, None, Some(x)) => parsing::preferred_float(x, options.default_float_size),
-            _ => unimplemented!("Should handle error here"), // TODO
+            _ => {
+                return Err(GenerationError::UnsupportedNumber {
+                    key_path: key_path.to_owned(),
+                    number: value.to_string(),
+                })
+            }
         },
         Value::String(value) => GenericValue::String(value),
         Value::Sequence(values) => GenericValue::Array(
             values
                 .into_iter()
-                .map(|value| yaml_to_raw_value(super_struct, super_key, value, options))
-                .collect(),
+                .enumerate()
+                .map(|(index, value)| {
+                    let element_path = parsing::element_path(key_path, index);
+                    yaml_to_raw_value(super_struct, super_key, &element_path, value, options)
+                })
+                .collect::<Result<_, _>>()?,
         ),
         Value::Mapping(values) => {
             let sub_struct_name = format!("{super_struct}__{super_key}");
             let values = values
                 .into_iter()
                 .map(|(key, value)| {
-                    let key = key.as_str().unwrap().to_owned();
-                    let value = yaml_to_raw_value(&sub_struct_name, &key, value, options);
-                    (key, value)
+                    let key = match key {
+                        Value::String(key) => key,
+                        key => {
+                            return Err(GenerationError::NonStringKey {
+                                key_path: key_path.to_owned(),
+                                key: yaml_key_string(&key),
+                            })
+                        }
+                    };
+                    let field_path = parsing::field_path(key_path, &key);
+                    let value =
+                        yaml_to_raw_value(&sub_struct_name, &key, &field_path, value, options)?;
+                    Ok((key, value))
                 })
-                .collect();
+                .collect::<Result<_, GenerationError>>()?;
             GenericValue::Struct(GenericStruct {
                 struct_name: sub_struct_name,
                 fields: values,
+                ..GenericStruct::default()
             })
         }
+    };
+
+    Ok(value)
+}
+
+/// Renders a YAML map key for an error message.
+fn yaml_key_string(key: &Value) -> String {
+    match serde_yaml::to_string(key) {
+        Ok(yaml) => yaml.trim_start_matches("---").trim().to_owned(),
+        Err(_) => format!("{key:?}"),
     }
 }
 
-#[allow(dead_code)]
 pub fn parse_map_keys(yaml: &str) -> Result<Vec<String>, GenerationError> {
     use linear_map::LinearMap;
 
-    let map: LinearMap<String, Value> = serde_yaml::from_str(yaml)
-        .map_err(|err| GenerationError::DeserializationFailed(err.to_string()))?;
+    let map: LinearMap<String, Value> =
+        serde_yaml::from_str(yaml).map_err(|err| yaml_error(err, yaml))?;
 
     Ok(map.into_iter().map(|pair| pair.0).collect())
 }
+
+/// Converts a YAML parse error, pointing at its location in the config.
+pub fn yaml_error(error: serde_yaml::Error, yaml: &str) -> GenerationError {
+    let line_column = error
+        .location()
+        .map(|location| (location.line(), location.column()));
+    DeserializationError::from_parser(error, yaml, line_column).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_string_keys() {
+        let yaml = "servers:\n  - ports:\n      http: 80\n  - ports:\n      443: https\n";
+        let error = parse_yaml(yaml, &StructOptions::default()).unwrap_err();
+        match error {
+            GenerationError::NonStringKey { key_path, key } => {
+                assert_eq!(key_path, "servers[1].ports");
+                assert_eq!(key, "443");
+            }
+            error => panic!("expected a non-string key error, got {error:?}"),
+        }
+    }
+}
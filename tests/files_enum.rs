@@ -0,0 +1,38 @@
+#![cfg(feature = "experimental-files-enum")]
+
+use std::path::Path;
+
+use config_to_struct::files_enum::{self, FilesEnumOptions};
+
+#[path = "fixtures/assets.rs"]
+mod assets;
+
+use assets::Asset;
+
+fn options() -> FilesEnumOptions {
+    FilesEnumOptions {
+        enum_name: "Asset".to_owned(),
+        include_bytes: true,
+        ..FilesEnumOptions::default()
+    }
+}
+
+#[test]
+fn generated_code_is_up_to_date() {
+    let code = files_enum::generate_files_enum("tests/fixtures/assets", &options()).unwrap();
+    let expected = std::fs::read_to_string("tests/fixtures/assets.rs").unwrap();
+
+    // Hidden files and subdirectories are skipped.
+    assert_eq!(code, expected.replace("\r\n", "\n"));
+}
+
+#[test]
+fn paths_and_bytes_resolve_from_the_manifest_dir() {
+    assert_eq!(Asset::ALL, [Asset::GreetingTxt, Asset::PlayerSpritePng]);
+
+    for &asset in Asset::ALL {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(asset.path());
+        assert_eq!(asset.bytes(), std::fs::read(path).unwrap());
+    }
+    assert_eq!(Asset::GreetingTxt.bytes(), b"hello\n");
+}
@@ -0,0 +1,34 @@
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Asset {
+    GreetingTxt,
+    PlayerSpritePng,
+}
+impl Asset {
+    pub const ALL: &'static [Asset] = &[Asset::GreetingTxt, Asset::PlayerSpritePng];
+}
+impl Asset {
+    pub const fn path(self) -> &'static str {
+        match self {
+            Asset::GreetingTxt => "tests/fixtures/assets/greeting.txt",
+            Asset::PlayerSpritePng => "tests/fixtures/assets/player_sprite.png",
+        }
+    }
+    pub const fn bytes(self) -> &'static [u8] {
+        match self {
+            Asset::GreetingTxt => {
+                include_bytes!(
+                    concat!(env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/assets/greeting.txt")
+                )
+            }
+            Asset::PlayerSpritePng => {
+                include_bytes!(
+                    concat!(env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/assets/player_sprite.png")
+                )
+            }
+        }
+    }
+}